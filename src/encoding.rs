@@ -0,0 +1,23 @@
+use crate::{BitcoinError, CompactSize};
+
+// CompactSize length followed by that many bytes, as used by scripts
+pub fn write_var_bytes(data: &[u8]) -> Vec<u8> {
+    let mut bytes = CompactSize::new(data.len() as u64).to_bytes();
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+pub fn read_var_bytes(bytes: &[u8]) -> Result<(Vec<u8>, usize), BitcoinError> {
+    let (data, consumed) = read_var_slice(bytes)?;
+    Ok((data.to_vec(), consumed))
+}
+
+pub fn read_var_slice(bytes: &[u8]) -> Result<(&[u8], usize), BitcoinError> {
+    let (length, consumed) = CompactSize::from_bytes(bytes)?;
+    let remaining = bytes.len() - consumed;
+    if length.value > remaining as u64 {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    let end = consumed + length.value as usize;
+    Ok((&bytes[consumed..end], end))
+}
@@ -0,0 +1,98 @@
+// Lets callers plug in their own SHA-256 implementation instead of the sha2 crate
+pub trait Sha256d {
+    fn sha256d(data: &[u8]) -> [u8; 32];
+}
+
+#[cfg(feature = "sha2")]
+pub struct Sha2;
+
+#[cfg(feature = "sha2")]
+impl Sha256d for Sha2 {
+    fn sha256d(data: &[u8]) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+        Sha256::digest(Sha256::digest(data)).into()
+    }
+}
+
+#[cfg(feature = "sha2")]
+pub fn sha256d(data: &[u8]) -> [u8; 32] {
+    Sha2::sha256d(data)
+}
+
+pub fn siphash24(key0: u64, key1: u64, data: &[u8]) -> u64 {
+    let mut v = [
+        key0 ^ 0x736f6d6570736575,
+        key1 ^ 0x646f72616e646f6d,
+        key0 ^ 0x6c7967656e657261,
+        key1 ^ 0x7465646279746573,
+    ];
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let m = u64::from_le_bytes(chunk.try_into().unwrap());
+        v[3] ^= m;
+        sip_round(&mut v);
+        sip_round(&mut v);
+        v[0] ^= m;
+    }
+
+    // Final block: remaining bytes plus the message length in the top byte
+    let mut last = [0u8; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = data.len() as u8;
+    let m = u64::from_le_bytes(last);
+    v[3] ^= m;
+    sip_round(&mut v);
+    sip_round(&mut v);
+    v[0] ^= m;
+
+    v[2] ^= 0xFF;
+    for _ in 0..4 {
+        sip_round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+fn sip_round(v: &mut [u64; 4]) {
+    v[0] = v[0].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(13) ^ v[0];
+    v[0] = v[0].rotate_left(32);
+    v[2] = v[2].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(16) ^ v[2];
+    v[0] = v[0].wrapping_add(v[3]);
+    v[3] = v[3].rotate_left(21) ^ v[0];
+    v[2] = v[2].wrapping_add(v[1]);
+    v[1] = v[1].rotate_left(17) ^ v[2];
+    v[2] = v[2].rotate_left(32);
+}
+
+pub fn murmur3_32(seed: u32, data: &[u8]) -> u32 {
+    const C1: u32 = 0xCC9E2D51;
+    const C2: u32 = 0x1B873593;
+
+    let mut h1 = seed;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k1 = u32::from_le_bytes(chunk.try_into().unwrap());
+        k1 = k1.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        h1 ^= k1;
+        h1 = h1.rotate_left(13).wrapping_mul(5).wrapping_add(0xE6546B64);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        let mut k1 = 0u32;
+        for (i, byte) in tail.iter().enumerate() {
+            k1 |= (*byte as u32) << (8 * i);
+        }
+        k1 = k1.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        h1 ^= k1;
+    }
+
+    h1 ^= data.len() as u32;
+    h1 ^= h1 >> 16;
+    h1 = h1.wrapping_mul(0x85EBCA6B);
+    h1 ^= h1 >> 13;
+    h1 = h1.wrapping_mul(0xC2B2AE35);
+    h1 ^ (h1 >> 16)
+}
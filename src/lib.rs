@@ -1,8 +1,22 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fmt;
+use std::io::{self, Read};
 use std::ops::Deref;
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub mod encoding;
+pub mod hashes;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+
+use hashes::murmur3_32;
+#[cfg(feature = "sha2")]
+pub use hashes::{sha256d, Sha2};
+pub use hashes::{siphash24, Sha256d};
+
+#[derive(PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct CompactSize {
     pub value: u64,
 }
@@ -11,6 +25,40 @@ pub struct CompactSize {
 pub enum BitcoinError {
     InsufficientBytes,
     InvalidFormat,
+    Io(std::io::ErrorKind),
+    SizeExceeded,
+    NonStandard,
+}
+
+pub fn read_array<const N: usize>(bytes: &[u8], offset: usize) -> Result<[u8; N], BitcoinError> {
+    let end = offset
+        .checked_add(N)
+        .ok_or(BitcoinError::InsufficientBytes)?;
+    let slice = bytes
+        .get(offset..end)
+        .ok_or(BitcoinError::InsufficientBytes)?;
+    Ok(slice.try_into().unwrap())
+}
+
+// Encodes a CompactSize into a stack buffer and returns the used prefix
+fn compact_size_bytes(buf: &mut [u8; 9], value: u64) -> &[u8] {
+    let width = CompactSize::new(value).encoding_width();
+    match width {
+        1 => buf[0] = value as u8,
+        3 => {
+            buf[0] = 0xFD;
+            buf[1..3].copy_from_slice(&(value as u16).to_le_bytes());
+        }
+        5 => {
+            buf[0] = 0xFE;
+            buf[1..5].copy_from_slice(&(value as u32).to_le_bytes());
+        }
+        _ => {
+            buf[0] = 0xFF;
+            buf[1..9].copy_from_slice(&value.to_le_bytes());
+        }
+    }
+    &buf[..width]
 }
 
 impl CompactSize {
@@ -18,21 +66,88 @@ impl CompactSize {
         CompactSize { value }
     }
 
+    pub fn to_bytes(&self) -> Vec<u8> {
+        compact_size_bytes(&mut [0; 9], self.value).to_vec()
+    }
+
+    pub fn encoding_marker(&self) -> u8 {
+        match self.value {
+            0..=252 => self.value as u8,
+            253..=65535 => 0xFD,
+            65536..=4294967295 => 0xFE,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn encoding_width(&self) -> usize {
+        match self.encoding_marker() {
+            0xFD => 3,
+            0xFE => 5,
+            0xFF => 9,
+            _ => 1,
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.is_empty() {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+
+        match bytes[0] {
+            0..=252 => Ok((CompactSize::new(bytes[0] as u64), 1)),
+            0xFD => {
+                let value = u16::from_le_bytes(read_array(bytes, 1)?) as u64;
+                Ok((CompactSize::new(value), 3))
+            }
+            0xFE => {
+                let value = u32::from_le_bytes(read_array(bytes, 1)?) as u64;
+                Ok((CompactSize::new(value), 5))
+            }
+            0xFF => {
+                let value = u64::from_le_bytes(read_array(bytes, 1)?);
+                Ok((CompactSize::new(value), 9))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for CompactSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompactSize")
+            .field("value", &self.value)
+            .field("marker", &format_args!("{:#04x}", self.encoding_marker()))
+            .field("width", &self.encoding_width())
+            .finish()
+    }
+}
+
+// Lightning's BOLT#1 BigSize: same markers as CompactSize, but big-endian and with
+// minimal encoding required
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BigSize {
+    pub value: u64,
+}
+
+impl BigSize {
+    pub fn new(value: u64) -> Self {
+        BigSize { value }
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         match self.value {
             0..=252 => bytes.push(self.value as u8),
             253..=65535 => {
                 bytes.push(0xFD);
-                bytes.extend_from_slice(&(self.value as u16).to_le_bytes());
+                bytes.extend_from_slice(&(self.value as u16).to_be_bytes());
             }
             65536..=4294967295 => {
                 bytes.push(0xFE);
-                bytes.extend_from_slice(&(self.value as u32).to_le_bytes());
+                bytes.extend_from_slice(&(self.value as u32).to_be_bytes());
             }
             _ => {
                 bytes.push(0xFF);
-                bytes.extend_from_slice(&self.value.to_le_bytes());
+                bytes.extend_from_slice(&self.value.to_be_bytes());
             }
         }
         bytes
@@ -43,36 +158,25 @@ impl CompactSize {
             return Err(BitcoinError::InsufficientBytes);
         }
 
-        match bytes[0] {
-            0..=252 => Ok((CompactSize::new(bytes[0] as u64), 1)),
-            0xFD => {
-                if bytes.len() < 3 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let value = u16::from_le_bytes([bytes[1], bytes[2]]) as u64;
-                Ok((CompactSize::new(value), 3))
-            }
-            0xFE => {
-                if bytes.len() < 5 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let value = u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]) as u64;
-                Ok((CompactSize::new(value), 5))
-            }
-            0xFF => {
-                if bytes.len() < 9 {
-                    return Err(BitcoinError::InsufficientBytes);
-                }
-                let value = u64::from_le_bytes([
-                    bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7], bytes[8],
-                ]);
-                Ok((CompactSize::new(value), 9))
-            }
+        let (value, consumed, min) = match bytes[0] {
+            0..=252 => (bytes[0] as u64, 1, 0),
+            0xFD => (u16::from_be_bytes(read_array(bytes, 1)?) as u64, 3, 0xFD),
+            0xFE => (
+                u32::from_be_bytes(read_array(bytes, 1)?) as u64,
+                5,
+                0x1_0000,
+            ),
+            0xFF => (u64::from_be_bytes(read_array(bytes, 1)?), 9, 0x1_0000_0000),
+        };
+        if value < min {
+            return Err(BitcoinError::InvalidFormat);
         }
+        Ok((BigSize::new(value), consumed))
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Txid(pub [u8; 32]);
 
 impl Serialize for Txid {
@@ -101,7 +205,127 @@ impl<'de> Deserialize<'de> for Txid {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+// BIP152: k0/k1 are the first two little-endian u64s of
+// SHA256(block header || nonce); the short id is the low 48 bits.
+pub fn short_txid(wtxid: &Txid, key0: u64, key1: u64) -> u64 {
+    siphash24(key0, key1, &wtxid.0) & 0x0000_FFFF_FFFF_FFFF
+}
+
+fn merkle_parent<H: Sha256d>(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut concat = [0u8; 64];
+    concat[..32].copy_from_slice(left);
+    concat[32..].copy_from_slice(right);
+    H::sha256d(&concat)
+}
+
+// Pairs up one tree level, duplicating the last hash when the count is odd
+fn merkle_level<H: Sha256d>(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| merkle_parent::<H>(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+#[cfg(feature = "sha2")]
+pub fn merkle_root(txids: &[Txid]) -> Option<[u8; 32]> {
+    merkle_root_with::<Sha2>(txids)
+}
+
+pub fn merkle_root_with<H: Sha256d>(txids: &[Txid]) -> Option<[u8; 32]> {
+    let mut level: Vec<[u8; 32]> = txids.iter().map(|txid| txid.0).collect();
+    while level.len() > 1 {
+        level = merkle_level::<H>(&level);
+    }
+    level.first().copied()
+}
+
+#[cfg(feature = "sha2")]
+pub fn merkle_proof(txids: &[Txid], index: usize) -> Option<Vec<[u8; 32]>> {
+    merkle_proof_with::<Sha2>(txids, index)
+}
+
+pub fn merkle_proof_with<H: Sha256d>(txids: &[Txid], index: usize) -> Option<Vec<[u8; 32]>> {
+    if index >= txids.len() {
+        return None;
+    }
+    let mut level: Vec<[u8; 32]> = txids.iter().map(|txid| txid.0).collect();
+    let mut position = index;
+    let mut branch = Vec::new();
+    while level.len() > 1 {
+        // A lone last node is paired with itself
+        let sibling = level.get(position ^ 1).unwrap_or(&level[position]);
+        branch.push(*sibling);
+        level = merkle_level::<H>(&level);
+        position /= 2;
+    }
+    Some(branch)
+}
+
+#[cfg(feature = "sha2")]
+pub fn verify_merkle_proof(
+    txid: &Txid,
+    branch: &[[u8; 32]],
+    index: usize,
+    expected_root: &[u8; 32],
+) -> bool {
+    verify_merkle_proof_with::<Sha2>(txid, branch, index, expected_root)
+}
+
+// The index bit at each level says whether the running hash is the right child
+pub fn verify_merkle_proof_with<H: Sha256d>(
+    txid: &Txid,
+    branch: &[[u8; 32]],
+    index: usize,
+    expected_root: &[u8; 32],
+) -> bool {
+    if branch.len() < usize::BITS as usize && index >> branch.len() != 0 {
+        return false;
+    }
+    let mut hash = txid.0;
+    for (level, sibling) in branch.iter().enumerate() {
+        hash = if (index >> level) & 1 == 1 {
+            merkle_parent::<H>(sibling, &hash)
+        } else {
+            merkle_parent::<H>(&hash, sibling)
+        };
+    }
+    hash == *expected_root
+}
+
+// Txids are shown byte-reversed, as in block explorers and RPC output
+impl fmt::Display for Txid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0.iter().rev() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+// Txid serializes as a hex string, so its schema is written by hand
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for Txid {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Txid".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "string",
+            "pattern": "^[0-9a-fA-F]{64}$"
+        })
+    }
+}
+
+#[cfg(feature = "schemars")]
+pub fn json_schema() -> schemars::Schema {
+    schemars::schema_for!(BitcoinTransaction)
+}
+
+// Field order makes the derived ordering txid-major, vout-minor, like Core's UTXO keys
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct OutPoint {
     pub txid: Txid,
     pub vout: u32,
@@ -123,17 +347,28 @@ impl OutPoint {
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        if bytes.len() < 36 {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        let mut txid = [0u8; 32];
-        txid.copy_from_slice(&bytes[0..32]);
-        let vout = u32::from_le_bytes([bytes[32], bytes[33], bytes[34], bytes[35]]);
+        let txid = read_array(bytes, 0)?;
+        let vout = u32::from_le_bytes(read_array(bytes, 32)?);
         Ok((OutPoint::new(txid, vout), 36))
     }
+
+    pub fn null() -> Self {
+        OutPoint::new([0u8; 32], u32::MAX)
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == OutPoint::null()
+    }
+}
+
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.txid, self.vout)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Script {
     pub bytes: Vec<u8>,
 }
@@ -143,22 +378,331 @@ impl Script {
         Script { bytes }
     }
 
+    pub fn as_script_ref(&self) -> ScriptRef<'_> {
+        ScriptRef(&self.bytes)
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        let length = CompactSize::new(self.bytes.len() as u64);
-        bytes.extend(length.to_bytes());
-        bytes.extend(&self.bytes);
-        bytes
+        self.as_script_ref().to_bytes()
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (length, consumed) = CompactSize::from_bytes(bytes)?;
-        let script_len = length.value as usize;
-        if bytes.len() < consumed + script_len {
+        let (script, consumed) = Script::from_bytes_ref(bytes)?;
+        Ok((script.to_script(), consumed))
+    }
+
+    pub fn from_bytes_ref(bytes: &[u8]) -> Result<(ScriptRef<'_>, usize), BitcoinError> {
+        let (script_bytes, consumed) = encoding::read_var_slice(bytes)?;
+        Ok((ScriptRef(script_bytes), consumed))
+    }
+
+    pub fn instructions(&self) -> Instructions<'_> {
+        self.as_script_ref().instructions()
+    }
+
+    pub fn pubkeys(&self) -> Vec<Vec<u8>> {
+        self.as_script_ref().pubkeys()
+    }
+
+    pub fn parse_multisig(&self) -> Option<MultisigInfo> {
+        self.as_script_ref().parse_multisig()
+    }
+
+    pub fn is_push_only(&self) -> bool {
+        self.as_script_ref().is_push_only()
+    }
+
+    pub fn has_minimal_pushes(&self) -> bool {
+        self.as_script_ref().has_minimal_pushes()
+    }
+
+    pub fn p2wpkh_script_code(&self) -> Option<Script> {
+        self.as_script_ref().p2wpkh_script_code()
+    }
+
+    pub fn legacy_sigop_count(&self) -> usize {
+        self.as_script_ref().legacy_sigop_count()
+    }
+
+    pub fn witness_program(&self) -> Option<(u8, &[u8])> {
+        self.as_script_ref().witness_program()
+    }
+
+    pub fn to_descriptor_fragment(&self) -> Option<String> {
+        self.as_script_ref().to_descriptor_fragment()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ScriptRef<'a>(pub &'a [u8]);
+
+impl<'a> ScriptRef<'a> {
+    pub fn to_script(&self) -> Script {
+        Script::new(self.0.to_vec())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encoding::write_var_bytes(self.0)
+    }
+
+    pub fn instructions(&self) -> Instructions<'a> {
+        Instructions {
+            bytes: self.0,
+            cursor: 0,
+        }
+    }
+
+    pub fn pubkeys(&self) -> Vec<Vec<u8>> {
+        self.instructions()
+            .map_while(Result::ok)
+            .filter_map(|instruction| match instruction {
+                Instruction::PushBytes(data) if is_sec_pubkey(data) => Some(data.to_vec()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn parse_multisig(&self) -> Option<MultisigInfo> {
+        let instructions: Vec<Instruction> = self.instructions().collect::<Result<_, _>>().ok()?;
+        let (first, rest) = instructions.split_first()?;
+        let (last, rest) = rest.split_last()?;
+        let (n_op, keys) = rest.split_last()?;
+
+        let m = small_int(first)?;
+        let n = small_int(n_op)?;
+        if *last != Instruction::Op(OP_CHECKMULTISIG) || m == 0 || m > n {
+            return None;
+        }
+
+        let pubkeys = keys
+            .iter()
+            .map(|instruction| match instruction {
+                Instruction::PushBytes(data) if is_sec_pubkey(data) => Some(data.to_vec()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        if pubkeys.len() != n as usize {
+            return None;
+        }
+
+        Some(MultisigInfo { m, n, pubkeys })
+    }
+
+    pub fn is_push_only(&self) -> bool {
+        self.instructions().all(|instruction| match instruction {
+            Ok(Instruction::PushBytes(_)) => true,
+            Ok(Instruction::Op(op)) => op <= OP_16,
+            Err(_) => false,
+        })
+    }
+
+    // BIP62 minimal push rule, as in Core's CheckMinimalPush
+    pub fn has_minimal_pushes(&self) -> bool {
+        let mut instructions = self.instructions();
+        loop {
+            let opcode = match self.0.get(instructions.cursor) {
+                Some(&opcode) => opcode,
+                None => return true,
+            };
+            let data = match instructions.next() {
+                Some(Ok(Instruction::PushBytes(data))) => data,
+                Some(Ok(Instruction::Op(_))) => continue,
+                _ => return false,
+            };
+            let minimal = match data {
+                [] => opcode == OP_0,
+                [n @ 1..=16] => opcode == OP_1 + n - 1,
+                [0x81] => opcode == OP_1NEGATE,
+                _ if data.len() <= 0x4B => opcode as usize == data.len(),
+                _ if data.len() <= 0xFF => opcode == OP_PUSHDATA1,
+                _ if data.len() <= 0xFFFF => opcode == OP_PUSHDATA2,
+                _ => true,
+            };
+            if !minimal {
+                return false;
+            }
+        }
+    }
+
+    pub fn p2wpkh_script_code(&self) -> Option<Script> {
+        // v0 witness program: OP_0 <20-byte key hash>
+        match self.0 {
+            [OP_0, 0x14, pubkey_hash @ ..] if pubkey_hash.len() == 20 => {
+                let mut bytes = vec![OP_DUP, OP_HASH160, 0x14];
+                bytes.extend_from_slice(pubkey_hash);
+                bytes.extend([OP_EQUALVERIFY, OP_CHECKSIG]);
+                Some(Script::new(bytes))
+            }
+            _ => None,
+        }
+    }
+
+    // BIP141: a version opcode followed by one direct push of 2 to 40 bytes
+    pub fn witness_program(&self) -> Option<(u8, &'a [u8])> {
+        let (&version_op, rest) = self.0.split_first()?;
+        let version = match version_op {
+            OP_0 => 0,
+            OP_1..=OP_16 => version_op - OP_1 + 1,
+            _ => return None,
+        };
+        let (&len, program) = rest.split_first()?;
+        if !(2..=40).contains(&len) || program.len() != len as usize {
+            return None;
+        }
+        Some((version, program))
+    }
+
+    // Descriptor-style summary of a standard scriptPubKey; pkh and sh show the hash
+    // since the key or redeem script is not known
+    pub fn to_descriptor_fragment(&self) -> Option<String> {
+        let (name, data) = match self.0 {
+            [OP_DUP, OP_HASH160, 0x14, hash @ .., OP_EQUALVERIFY, OP_CHECKSIG]
+                if hash.len() == 20 =>
+            {
+                ("pkh", hash)
+            }
+            [OP_HASH160, 0x14, hash @ .., OP_EQUAL] if hash.len() == 20 => ("sh", hash),
+            _ => match self.witness_program()? {
+                (0, program) if program.len() == 20 => ("wpkh", program),
+                (0, program) if program.len() == 32 => ("wsh", program),
+                (1, program) if program.len() == 32 => ("tr", program),
+                _ => return None,
+            },
+        };
+        Some(format!("{}({})", name, hex::encode(data)))
+    }
+
+    // Like Core's inaccurate count: multisig always costs the maximum 20 keys
+    pub fn legacy_sigop_count(&self) -> usize {
+        self.instructions()
+            .map_while(Result::ok)
+            .map(|instruction| match instruction {
+                Instruction::Op(OP_CHECKSIG | OP_CHECKSIGVERIFY) => 1,
+                Instruction::Op(OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY) => {
+                    MAX_PUBKEYS_PER_MULTISIG
+                }
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+impl Deref for ScriptRef<'_> {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MultisigInfo {
+    pub m: u8,
+    pub n: u8,
+    pub pubkeys: Vec<Vec<u8>>,
+}
+
+fn small_int(instruction: &Instruction) -> Option<u8> {
+    match *instruction {
+        Instruction::Op(op @ OP_1..=OP_16) => Some(op - OP_1 + 1),
+        _ => None,
+    }
+}
+
+fn is_sec_pubkey(data: &[u8]) -> bool {
+    match data.len() {
+        33 => data[0] == 0x02 || data[0] == 0x03,
+        65 => data[0] == 0x04,
+        _ => false,
+    }
+}
+
+pub const OP_0: u8 = 0x00;
+pub const OP_PUSHDATA1: u8 = 0x4C;
+pub const OP_PUSHDATA2: u8 = 0x4D;
+pub const OP_PUSHDATA4: u8 = 0x4E;
+pub const OP_1NEGATE: u8 = 0x4F;
+pub const OP_1: u8 = 0x51;
+pub const OP_16: u8 = 0x60;
+pub const OP_DUP: u8 = 0x76;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_EQUALVERIFY: u8 = 0x88;
+pub const OP_HASH160: u8 = 0xA9;
+pub const OP_CHECKSIG: u8 = 0xAC;
+pub const OP_CHECKSIGVERIFY: u8 = 0xAD;
+pub const OP_CHECKMULTISIG: u8 = 0xAE;
+pub const OP_CHECKMULTISIGVERIFY: u8 = 0xAF;
+
+pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Instruction<'a> {
+    PushBytes(&'a [u8]),
+    Op(u8),
+}
+
+#[derive(Debug, Clone)]
+pub struct Instructions<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> Instructions<'a> {
+    fn read_push(&mut self, len_width: usize) -> Result<Instruction<'a>, BitcoinError> {
+        let start = self.cursor + 1;
+        if self.bytes.len() < start + len_width {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let mut len_bytes = [0u8; 4];
+        len_bytes[..len_width].copy_from_slice(&self.bytes[start..start + len_width]);
+        let len = u32::from_le_bytes(len_bytes) as usize;
+        self.take_push(start + len_width, len)
+    }
+
+    fn take_push(&mut self, start: usize, len: usize) -> Result<Instruction<'a>, BitcoinError> {
+        if self.bytes.len() - start < len {
             return Err(BitcoinError::InsufficientBytes);
         }
-        let script_bytes = bytes[consumed..consumed + script_len].to_vec();
-        Ok((Script::new(script_bytes), consumed + script_len))
+        self.cursor = start + len;
+        Ok(Instruction::PushBytes(&self.bytes[start..start + len]))
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<Instruction<'a>, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let opcode = *self.bytes.get(self.cursor)?;
+        let result = match opcode {
+            OP_0 => {
+                self.cursor += 1;
+                Ok(Instruction::PushBytes(&[]))
+            }
+            0x01..=0x4B => self.take_push(self.cursor + 1, opcode as usize),
+            OP_PUSHDATA1 => self.read_push(1),
+            OP_PUSHDATA2 => self.read_push(2),
+            OP_PUSHDATA4 => self.read_push(4),
+            _ => {
+                self.cursor += 1;
+                Ok(Instruction::Op(opcode))
+            }
+        };
+        // A truncated push ends the stream after reporting the error
+        if result.is_err() {
+            self.cursor = self.bytes.len();
+        }
+        Some(result)
+    }
+}
+
+// Keep fuzzed scripts within the standard scriptSig size
+#[cfg(feature = "arbitrary")]
+const MAX_ARBITRARY_SCRIPT_LEN: usize = 1650;
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Script {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let len = u.int_in_range(0..=MAX_ARBITRARY_SCRIPT_LEN)?;
+        Ok(Script::new(u.bytes(len)?.to_vec()))
     }
 }
 
@@ -170,6 +714,8 @@ impl Deref for Script {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct TransactionInput {
     pub previous_output: OutPoint,
     pub script_sig: Script,
@@ -186,40 +732,41 @@ impl TransactionInput {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+        let mut bytes = Vec::with_capacity(self.serialized_size());
         bytes.extend(self.previous_output.to_bytes());
         bytes.extend(self.script_sig.to_bytes());
         bytes.extend_from_slice(&self.sequence.to_le_bytes());
         bytes
     }
 
-    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let mut cursor = 0;
-        let (previous_output, consumed) = OutPoint::from_bytes(&bytes[cursor..])?;
-        cursor += consumed;
+    pub fn serialized_size(&self) -> usize {
+        let script_len = CompactSize::new(self.script_sig.len() as u64);
+        36 + script_len.encoding_width() + self.script_sig.len() + 4
+    }
 
-        let (script_sig, consumed) = Script::from_bytes(&bytes[cursor..])?;
-        cursor += consumed;
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (input, consumed) = TransactionInputView::from_bytes(bytes)?;
+        Ok((input.into_owned(), consumed))
+    }
 
-        if bytes.len() < cursor + 4 {
-            return Err(BitcoinError::InsufficientBytes);
+    // P2SH-wrapped segwit: the scriptSig is a single push of the witness program script
+    pub fn nested_witness_program(&self) -> Option<(u8, Vec<u8>)> {
+        let mut instructions = self.script_sig.instructions();
+        let redeem_script = match instructions.next()? {
+            Ok(Instruction::PushBytes(data)) => ScriptRef(data),
+            _ => return None,
+        };
+        if instructions.next().is_some() {
+            return None;
         }
-        let sequence = u32::from_le_bytes([
-            bytes[cursor],
-            bytes[cursor + 1],
-            bytes[cursor + 2],
-            bytes[cursor + 3],
-        ]);
-        cursor += 4;
-
-        Ok((
-            TransactionInput::new(previous_output, script_sig, sequence),
-            cursor,
-        ))
+        let (version, program) = redeem_script.witness_program()?;
+        Some((version, program.to_vec()))
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct BitcoinTransaction {
     pub version: u32,
     pub inputs: Vec<TransactionInput>,
@@ -236,7 +783,7 @@ impl BitcoinTransaction {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+        let mut bytes = Vec::with_capacity(self.serialized_size());
 
         // Version (4 bytes LE)
         bytes.extend_from_slice(&self.version.to_le_bytes());
@@ -253,44 +800,1253 @@ impl BitcoinTransaction {
         // Lock time (4 bytes LE)
         bytes.extend_from_slice(&self.lock_time.to_le_bytes());
 
+        debug_assert_eq!(bytes.len(), self.serialized_size());
         bytes
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let mut cursor = 0;
+        BitcoinTransaction::from_bytes_with(bytes, &ParseOptions::permissive())
+    }
+
+    pub fn serialized_size(&self) -> usize {
+        let input_count = CompactSize::new(self.inputs.len() as u64);
+        let inputs: usize = self.inputs.iter().map(|i| i.serialized_size()).sum();
+        4 + input_count.encoding_width() + inputs + 4
+    }
 
-        // Read version (4 bytes LE)
-        if bytes.len() < 4 {
+    // Same bytes as to_bytes, written without allocating
+    pub fn encode_to_slice(&self, out: &mut [u8]) -> Result<usize, BitcoinError> {
+        let size = self.serialized_size();
+        if out.len() < size {
             return Err(BitcoinError::InsufficientBytes);
         }
-        let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+        let mut cursor = 0;
+        let mut put = |bytes: &[u8]| {
+            out[cursor..cursor + bytes.len()].copy_from_slice(bytes);
+            cursor += bytes.len();
+        };
+        put(&self.version.to_le_bytes());
+        put(compact_size_bytes(&mut [0; 9], self.inputs.len() as u64));
+        for input in &self.inputs {
+            put(&input.previous_output.txid.0);
+            put(&input.previous_output.vout.to_le_bytes());
+            put(compact_size_bytes(
+                &mut [0; 9],
+                input.script_sig.len() as u64,
+            ));
+            put(&input.script_sig);
+            put(&input.sequence.to_le_bytes());
+        }
+        put(&self.lock_time.to_le_bytes());
+
+        Ok(size)
+    }
+
+    pub fn from_bytes_with(
+        bytes: &[u8],
+        options: &ParseOptions,
+    ) -> Result<(Self, usize), BitcoinError> {
+        let (tx, consumed, _) = BitcoinTransaction::parse(bytes, options)?;
+        Ok((tx, consumed))
+    }
+
+    pub fn from_bytes_with_canonicality(bytes: &[u8]) -> Result<(Self, usize, bool), BitcoinError> {
+        BitcoinTransaction::parse(bytes, &ParseOptions::permissive())
+    }
+
+    fn parse(bytes: &[u8], options: &ParseOptions) -> Result<(Self, usize, bool), BitcoinError> {
+        let parsed = parse_transaction(bytes, options, TransactionInputView::into_owned)?;
+        let tx = BitcoinTransaction::new(parsed.version, parsed.inputs, parsed.lock_time);
+        Ok((tx, parsed.consumed, parsed.canonical))
+    }
+
+    // Legacy sighash preimage: the serialized transaction followed by a 4-byte LE sighash type
+    pub fn from_sighash_preimage(bytes: &[u8]) -> Result<(Self, u32), BitcoinError> {
+        let (tx, consumed) = BitcoinTransaction::from_bytes(bytes)?;
+        let sighash_type = u32::from_le_bytes(read_array(bytes, consumed)?);
+        if consumed + 4 != bytes.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok((tx, sighash_type))
+    }
+
+    // to_bytes always emits minimal CompactSizes; scriptSig contents are left untouched
+    pub fn canonicalize(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    pub fn from_bytes_partial(bytes: &[u8]) -> (PartialTransaction, usize) {
+        let mut partial = PartialTransaction::default();
+        let mut cursor = 0;
+
+        let Ok(version) = read_array(bytes, cursor) else {
+            return (partial, cursor);
+        };
+        partial.version = Some(u32::from_le_bytes(version));
         cursor += 4;
 
-        // Read input count
-        let (input_count, consumed) = CompactSize::from_bytes(&bytes[cursor..])?;
+        let Ok((input_count, consumed)) = CompactSize::from_bytes(&bytes[cursor..]) else {
+            return (partial, cursor);
+        };
+        partial.input_count = Some(input_count.value);
         cursor += consumed;
 
-        // Read inputs
-        let mut inputs = Vec::with_capacity(input_count.value as usize);
         for _ in 0..input_count.value {
-            let (input, consumed) = TransactionInput::from_bytes(&bytes[cursor..])?;
-            inputs.push(input);
+            let Ok((input, consumed)) = TransactionInput::from_bytes(&bytes[cursor..]) else {
+                return (partial, cursor);
+            };
+            partial.inputs.push(input);
             cursor += consumed;
         }
 
-        // Read lock_time
-        if bytes.len() < cursor + 4 {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        let lock_time = u32::from_le_bytes([
-            bytes[cursor],
-            bytes[cursor + 1],
-            bytes[cursor + 2],
-            bytes[cursor + 3],
-        ]);
+        let Ok(lock_time) = read_array(bytes, cursor) else {
+            return (partial, cursor);
+        };
+        partial.lock_time = Some(u32::from_le_bytes(lock_time));
         cursor += 4;
 
-        Ok((BitcoinTransaction::new(version, inputs, lock_time), cursor))
+        (partial, cursor)
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, BitcoinError> {
+        let s = s.trim_start();
+        let s = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .unwrap_or(s);
+        let cleaned: String = s.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+        let bytes = hex::decode(cleaned).map_err(|_| BitcoinError::InvalidFormat)?;
+        let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes)?;
+        if consumed != bytes.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(tx)
+    }
+
+    pub fn timelock_summary(&self) -> TimelockSummary {
+        // lock_time is only enforced when some input opts in with a non-final sequence
+        let absolute =
+            if self.lock_time == 0 || self.inputs.iter().all(|i| i.sequence == SEQUENCE_FINAL) {
+                AbsoluteLock::None
+            } else if self.lock_time < LOCKTIME_THRESHOLD {
+                AbsoluteLock::Height(self.lock_time)
+            } else {
+                AbsoluteLock::Time(self.lock_time)
+            };
+
+        let mut min_relative_blocks = None;
+        let mut min_relative_seconds = None;
+        // BIP68 relative locks only apply from version 2 onwards
+        if self.version >= 2 {
+            for input in &self.inputs {
+                if input.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+                    continue;
+                }
+                let value = (input.sequence & SEQUENCE_LOCKTIME_MASK) as u16;
+                if input.sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+                    let seconds = value as u32 * 512;
+                    min_relative_seconds =
+                        Some(min_relative_seconds.map_or(seconds, |s: u32| s.min(seconds)));
+                } else {
+                    min_relative_blocks =
+                        Some(min_relative_blocks.map_or(value, |b: u16| b.min(value)));
+                }
+            }
+        }
+
+        TimelockSummary {
+            absolute,
+            min_relative_blocks,
+            min_relative_seconds,
+        }
+    }
+
+    pub fn display_summary(&self) -> String {
+        format!(
+            "version {}, {} input(s), {} bytes, lock time {}",
+            self.version,
+            self.inputs.len(),
+            self.serialized_size(),
+            self.lock_time
+        )
+    }
+
+    pub fn display_detailed(&self) -> String {
+        let summary = self.timelock_summary();
+        let mut out = self.to_string();
+        out.push_str(&format!("Size: {} bytes\n", self.serialized_size()));
+        out.push_str(&format!("Absolute Lock: {:?}\n", summary.absolute));
+        if let Some(blocks) = summary.min_relative_blocks {
+            out.push_str(&format!("Min Relative Lock: {} blocks\n", blocks));
+        }
+        if let Some(seconds) = summary.min_relative_seconds {
+            out.push_str(&format!("Min Relative Lock: {} seconds\n", seconds));
+        }
+        out
+    }
+
+    // Hash over everything except the scriptSigs, so malleated copies share it
+    #[cfg(feature = "sha2")]
+    pub fn normalized_txid(&self) -> [u8; 32] {
+        self.normalized_txid_with::<Sha2>()
+    }
+
+    pub fn normalized_txid_with<H: Sha256d>(&self) -> [u8; 32] {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend(CompactSize::new(self.inputs.len() as u64).to_bytes());
+        for input in &self.inputs {
+            bytes.extend(input.previous_output.to_bytes());
+            bytes.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.lock_time.to_le_bytes());
+        H::sha256d(&bytes)
+    }
+
+    // Only scriptSigs are counted; this crate does not model outputs
+    pub fn legacy_sigop_count(&self) -> usize {
+        self.inputs
+            .iter()
+            .map(|input| input.script_sig.legacy_sigop_count())
+            .sum()
+    }
+
+    // Core's GetVirtualTransactionSize: sigop cost is legacy sigops scaled by the witness factor
+    pub fn sigop_adjusted_vsize(&self) -> usize {
+        let sigop_cost = self.legacy_sigop_count() * WITNESS_SCALE_FACTOR;
+        self.weight()
+            .max(sigop_cost * DEFAULT_BYTES_PER_SIGOP)
+            .div_ceil(WITNESS_SCALE_FACTOR)
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_output.is_null()
+    }
+
+    pub fn coinbase_input(&self) -> Option<&TransactionInput> {
+        if self.is_coinbase() {
+            self.inputs.first()
+        } else {
+            None
+        }
+    }
+
+    // BIP37 matching over the parts modelled here: spent outpoints and scriptSig pushes
+    pub fn matches_bloom(&self, filter: &BloomFilter) -> bool {
+        self.inputs.iter().any(|input| {
+            filter.contains(&input.previous_output.to_bytes())
+                || input
+                    .script_sig
+                    .instructions()
+                    .map_while(Result::ok)
+                    .any(|instruction| match instruction {
+                        Instruction::PushBytes(data) => !data.is_empty() && filter.contains(data),
+                        Instruction::Op(_) => false,
+                    })
+        })
+    }
+
+    pub fn hashing_chunks(&self) -> Vec<Chunk> {
+        let mut chunks = vec![
+            Chunk::new(ChunkKind::Version, self.version.to_le_bytes().to_vec()),
+            Chunk::new(
+                ChunkKind::InputCount,
+                CompactSize::new(self.inputs.len() as u64).to_bytes(),
+            ),
+        ];
+        for (index, input) in self.inputs.iter().enumerate() {
+            chunks.push(Chunk::new(
+                ChunkKind::PreviousOutput(index),
+                input.previous_output.to_bytes(),
+            ));
+            chunks.push(Chunk::new(
+                ChunkKind::ScriptSig(index),
+                input.script_sig.to_bytes(),
+            ));
+            chunks.push(Chunk::new(
+                ChunkKind::Sequence(index),
+                input.sequence.to_le_bytes().to_vec(),
+            ));
+        }
+        chunks.push(Chunk::new(
+            ChunkKind::LockTime,
+            self.lock_time.to_le_bytes().to_vec(),
+        ));
+        chunks
+    }
+
+    // No witness data is modelled, so every byte carries the full weight of four units
+    pub fn weight(&self) -> usize {
+        self.serialized_size() * 4
+    }
+
+    // Raw bytes signing adds, with input_types matched to inputs by position. Uses the
+    // worst-case sizes from InputType::max_signature_size. Inputs without a type are
+    // left as they are and only get an empty witness stack if the tx becomes segwit;
+    // extra types are ignored.
+    pub fn signing_overhead(&self, input_types: &[InputType]) -> usize {
+        let input_types = &input_types[..input_types.len().min(self.inputs.len())];
+        let mut overhead = 0;
+        for (input, input_type) in self.inputs.iter().zip(input_types) {
+            let current = CompactSize::new(input.script_sig.len() as u64).encoding_width()
+                + input.script_sig.len();
+            let script_sig = input_type.max_signature_size().0;
+            let signed = CompactSize::new(script_sig as u64).encoding_width() + script_sig;
+            overhead += signed.saturating_sub(current);
+        }
+        if input_types.iter().any(InputType::is_witness) {
+            // Marker and flag, then one stack per input (empty ones for legacy inputs)
+            overhead += 2;
+            overhead += input_types
+                .iter()
+                .map(|input_type| input_type.max_signature_size().1.max(1))
+                .sum::<usize>();
+            overhead += self.inputs.len() - input_types.len();
+        }
+        overhead
+    }
+
+    pub fn weight_breakdown(&self) -> WeightBreakdown {
+        let input_count = CompactSize::new(self.inputs.len() as u64);
+        WeightBreakdown {
+            overhead: (4 + input_count.encoding_width() + 4) * WITNESS_SCALE_FACTOR,
+            inputs: self
+                .inputs
+                .iter()
+                .map(|input| input.serialized_size() * WITNESS_SCALE_FACTOR)
+                .collect(),
+        }
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(4)
+    }
+
+    pub fn required_fee(&self, sat_per_vbyte: f64) -> u64 {
+        fee_for_vsize(self.vsize(), sat_per_vbyte)
+    }
+
+    #[cfg(feature = "base64")]
+    pub fn to_base64(&self) -> String {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD.encode(self.to_bytes())
+    }
+
+    #[cfg(feature = "base64")]
+    pub fn from_base64(s: &str) -> Result<Self, BitcoinError> {
+        use base64::Engine;
+        let cleaned: String = s.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(cleaned)
+            .map_err(|_| BitcoinError::InvalidFormat)?;
+        let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes)?;
+        if consumed != bytes.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(tx)
+    }
+
+    // BIP125: any input with a sequence below 0xFFFFFFFE opts in to replacement
+    pub fn signals_rbf(&self) -> bool {
+        self.inputs
+            .iter()
+            .any(|input| input.sequence < SEQUENCE_FINAL - 1)
+    }
+
+    // Distinct parent txids in first-seen order; the coinbase null outpoint is skipped
+    pub fn depends_on(&self) -> Vec<Txid> {
+        let mut seen = HashSet::new();
+        self.inputs
+            .iter()
+            .map(|input| &input.previous_output)
+            .filter(|outpoint| !outpoint.is_null())
+            .filter(|outpoint| seen.insert(&outpoint.txid))
+            .map(|outpoint| outpoint.txid.clone())
+            .collect()
+    }
+
+    pub fn spent_outpoints_display(&self) -> Vec<String> {
+        self.inputs
+            .iter()
+            .map(|input| input.previous_output.to_string())
+            .collect()
+    }
+
+    // Only scriptSigs exist in this model; there are no scriptPubKeys to include
+    pub fn all_scripts(&self) -> Vec<&Script> {
+        self.inputs.iter().map(|input| &input.script_sig).collect()
+    }
+
+    pub fn is_truc(&self) -> bool {
+        self.version == TRUC_VERSION
+    }
+
+    // BIP431 topology limits; `ancestors` is the number of unconfirmed ancestors
+    pub fn check_truc_policy(&self, ancestors: usize) -> Result<(), BitcoinError> {
+        if !self.is_truc() {
+            return Ok(());
+        }
+        if self.vsize() > TRUC_MAX_VSIZE {
+            return Err(BitcoinError::SizeExceeded);
+        }
+        if ancestors > TRUC_ANCESTOR_LIMIT {
+            return Err(BitcoinError::NonStandard);
+        }
+        // A TRUC child of an unconfirmed TRUC parent gets a tighter size limit
+        if ancestors == 1 && self.vsize() > TRUC_CHILD_MAX_VSIZE {
+            return Err(BitcoinError::SizeExceeded);
+        }
+        Ok(())
+    }
+
+    pub fn explain(&self) -> String {
+        let mut sentences = Vec::new();
+        if self.is_coinbase() {
+            sentences.push("This is a coinbase transaction creating new coins.".to_string());
+        } else {
+            sentences.push(format!(
+                "This version {} transaction spends {} input{}.",
+                self.version,
+                self.inputs.len(),
+                if self.inputs.len() == 1 { "" } else { "s" }
+            ));
+        }
+        sentences.push(format!(
+            "It signals RBF: {}.",
+            if self.signals_rbf() { "yes" } else { "no" }
+        ));
+        sentences.push(match self.timelock_summary().absolute {
+            AbsoluteLock::None => "It has no absolute lock time.".to_string(),
+            AbsoluteLock::Height(height) => format!("It cannot be mined before block {}.", height),
+            AbsoluteLock::Time(time) => format!("It cannot be mined before unix time {}.", time),
+        });
+        sentences.push(format!(
+            "Is SegWit: no. Weight: {} WU ({} vbytes).",
+            self.weight(),
+            self.vsize()
+        ));
+        sentences.join(" ")
+    }
+
+    // One aligned row per input; values are unknown without the spent outputs
+    pub fn to_table(&self) -> String {
+        let mut rows = vec![[
+            "#".to_string(),
+            "Outpoint".to_string(),
+            "Sequence".to_string(),
+            "ScriptSig (bytes)".to_string(),
+        ]];
+        for (index, input) in self.inputs.iter().enumerate() {
+            rows.push([
+                index.to_string(),
+                input.previous_output.to_string(),
+                format!("0x{:08x}", input.sequence),
+                input.script_sig.len().to_string(),
+            ]);
+        }
+
+        let mut widths = [0; 4];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        let mut out = String::new();
+        for row in &rows {
+            let line = format!(
+                "{:>w0$}  {:<w1$}  {:<w2$}  {:>w3$}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+                w3 = widths[3],
+            );
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    // BIP143 hashPrevouts
+    #[cfg(feature = "sha2")]
+    pub fn prevouts_hash(&self) -> [u8; 32] {
+        self.prevouts_hash_with::<Sha2>()
+    }
+
+    pub fn prevouts_hash_with<H: Sha256d>(&self) -> [u8; 32] {
+        let mut bytes = Vec::with_capacity(self.inputs.len() * 36);
+        for input in &self.inputs {
+            bytes.extend(input.previous_output.to_bytes());
+        }
+        H::sha256d(&bytes)
+    }
+
+    // BIP143 hashSequence
+    #[cfg(feature = "sha2")]
+    pub fn sequences_hash(&self) -> [u8; 32] {
+        self.sequences_hash_with::<Sha2>()
+    }
+
+    pub fn sequences_hash_with<H: Sha256d>(&self) -> [u8; 32] {
+        let mut bytes = Vec::with_capacity(self.inputs.len() * 4);
+        for input in &self.inputs {
+            bytes.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+        H::sha256d(&bytes)
+    }
+
+    // Fixed-shape transaction for benchmarks; inputs spend the null outpoint
+    // with empty scriptSigs
+    #[cfg(feature = "testing")]
+    pub fn dummy(num_inputs: usize) -> BitcoinTransaction {
+        let input = TransactionInput::new(OutPoint::null(), Script::new(vec![]), SEQUENCE_FINAL);
+        BitcoinTransaction::new(1, vec![input; num_inputs], 0)
+    }
+
+    pub fn with_all_sequences(&self, seq: u32) -> BitcoinTransaction {
+        let mut tx = self.clone();
+        for input in &mut tx.inputs {
+            input.sequence = seq;
+        }
+        tx
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ChunkKind {
+    Version,
+    InputCount,
+    PreviousOutput(usize),
+    // Includes the CompactSize length prefix
+    ScriptSig(usize),
+    Sequence(usize),
+    LockTime,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Chunk {
+    pub kind: ChunkKind,
+    pub bytes: Vec<u8>,
+}
+
+impl Chunk {
+    pub fn new(kind: ChunkKind, bytes: Vec<u8>) -> Self {
+        Chunk { kind, bytes }
+    }
+}
+
+pub const MAX_BLOOM_FILTER_SIZE: usize = 36_000;
+pub const MAX_BLOOM_HASH_FUNCS: u32 = 50;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BloomFilter {
+    pub data: Vec<u8>,
+    pub hash_funcs: u32,
+    pub tweak: u32,
+}
+
+impl BloomFilter {
+    pub fn new(elements: u32, fp_rate: f64, tweak: u32) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let bits = -1.0 / (ln2 * ln2) * elements.max(1) as f64 * fp_rate.ln();
+        let size = ((bits / 8.0) as usize).clamp(1, MAX_BLOOM_FILTER_SIZE);
+        // Integer division before scaling by ln 2, exactly as CBloomFilter does
+        let hash_funcs = ((size * 8 / elements.max(1) as usize) as f64 * ln2) as u32;
+        BloomFilter {
+            data: vec![0u8; size],
+            hash_funcs: hash_funcs.clamp(1, MAX_BLOOM_HASH_FUNCS),
+            tweak,
+        }
+    }
+
+    fn bit_index(&self, hash_num: u32, data: &[u8]) -> usize {
+        let seed = hash_num.wrapping_mul(0xFBA4C795).wrapping_add(self.tweak);
+        murmur3_32(seed, data) as usize % (self.data.len() * 8)
+    }
+
+    pub fn insert(&mut self, data: &[u8]) {
+        // An empty filter matches nothing, as in Core (CVE-2013-5700)
+        if self.data.is_empty() {
+            return;
+        }
+        for i in 0..self.hash_funcs {
+            let index = self.bit_index(i, data);
+            self.data[index >> 3] |= 1 << (index & 7);
+        }
+    }
+
+    pub fn contains(&self, data: &[u8]) -> bool {
+        !self.data.is_empty()
+            && (0..self.hash_funcs).all(|i| {
+                let index = self.bit_index(i, data);
+                self.data[index >> 3] & (1 << (index & 7)) != 0
+            })
+    }
+}
+
+// Weight units per component; inputs carry no witness, so each is its size times 4
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WeightBreakdown {
+    pub overhead: usize,
+    pub inputs: Vec<usize>,
+}
+
+impl WeightBreakdown {
+    pub fn total(&self) -> usize {
+        self.overhead + self.inputs.iter().sum::<usize>()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InputType {
+    P2pkh,
+    P2wpkh,
+    P2wshMultisig { m: u8, n: u8 },
+    P2trKeypath,
+}
+
+// Typical signature push: 70-byte DER signature plus the sighash byte
+const TYPICAL_ECDSA_SIG_LEN: usize = 71;
+const SCHNORR_SIG_LEN: usize = 64;
+// Worst case: 72-byte high-R DER signature, and a Schnorr signature with an explicit
+// sighash byte. Low-R grinding would allow a tighter 71-byte bound.
+const MAX_ECDSA_SIG_LEN: usize = 73;
+const MAX_SCHNORR_SIG_LEN: usize = 65;
+const COMPRESSED_PUBKEY_LEN: usize = 33;
+
+impl InputType {
+    pub fn script_sig_size(&self) -> usize {
+        self.signed_sizes(TYPICAL_ECDSA_SIG_LEN, SCHNORR_SIG_LEN).0
+    }
+
+    // Serialized witness stack including its item count; 0 for legacy inputs
+    pub fn witness_size(&self) -> usize {
+        self.signed_sizes(TYPICAL_ECDSA_SIG_LEN, SCHNORR_SIG_LEN).1
+    }
+
+    // Worst-case (scriptSig, witness) sizes, so fee estimates never undershoot
+    pub fn max_signature_size(&self) -> (usize, usize) {
+        self.signed_sizes(MAX_ECDSA_SIG_LEN, MAX_SCHNORR_SIG_LEN)
+    }
+
+    fn signed_sizes(&self, ecdsa_sig: usize, schnorr_sig: usize) -> (usize, usize) {
+        match *self {
+            InputType::P2pkh => (1 + ecdsa_sig + 1 + COMPRESSED_PUBKEY_LEN, 0),
+            InputType::P2wpkh => (0, 1 + 1 + ecdsa_sig + 1 + COMPRESSED_PUBKEY_LEN),
+            InputType::P2wshMultisig { m, n } => {
+                // OP_m <n pubkeys> OP_n OP_CHECKMULTISIG
+                let witness_script = 3 + n as usize * (1 + COMPRESSED_PUBKEY_LEN);
+                let items = 1 + 1 + m as usize * (1 + ecdsa_sig);
+                let script_push = CompactSize::new(witness_script as u64).encoding_width();
+                (0, items + script_push + witness_script)
+            }
+            InputType::P2trKeypath => (0, 1 + 1 + schnorr_sig),
+        }
+    }
+
+    pub fn is_witness(&self) -> bool {
+        !matches!(self, InputType::P2pkh)
+    }
+
+    pub fn weight(&self) -> usize {
+        let (script_sig, witness) = self.max_signature_size();
+        let base = 36 + CompactSize::new(script_sig as u64).encoding_width() + script_sig + 4;
+        base * 4 + witness
+    }
+}
+
+// Estimates a signed Bitcoin transaction (version 2 layout with outputs) from input
+// kinds and output scriptPubKeys, using worst-case signature sizes.
+pub fn estimate_vsize(input_types: &[InputType], output_scripts: &[Script]) -> usize {
+    let mut base = 4 + CompactSize::new(input_types.len() as u64).encoding_width();
+    for input_type in input_types {
+        let script_sig = input_type.max_signature_size().0;
+        base += 36 + CompactSize::new(script_sig as u64).encoding_width() + script_sig + 4;
+    }
+    base += CompactSize::new(output_scripts.len() as u64).encoding_width();
+    for script in output_scripts {
+        base += 8 + script.to_bytes().len();
+    }
+    base += 4;
+
+    let mut witness = 0;
+    if input_types.iter().any(InputType::is_witness) {
+        // Marker and flag, then one stack per input (legacy inputs get an empty one)
+        witness += 2;
+        for input_type in input_types {
+            witness += input_type.max_signature_size().1.max(1);
+        }
+    }
+
+    (base * 4 + witness).div_ceil(4)
+}
+
+// ceil(vsize * rate), so the fee never falls below the requested rate. Float noise is
+// trimmed before the ceiling so that e.g. 1.1 sat/vB over 100 vbytes is exactly 110
+// sats rather than 111.
+fn fee_for_vsize(vsize: usize, sat_per_vbyte: f64) -> u64 {
+    if !sat_per_vbyte.is_finite() || sat_per_vbyte <= 0.0 {
+        return 0;
+    }
+    (vsize as f64 * sat_per_vbyte - 1e-6).ceil() as u64
+}
+
+// Fee added by one more input of this type. The segwit marker and flag paid by the
+// first witness input are not included.
+pub fn marginal_cost_of_input(input_type: InputType, fee_rate: f64) -> u64 {
+    fee_for_vsize(input_type.weight().div_ceil(4), fee_rate)
+}
+
+pub const SATS_PER_BTC: u64 = 100_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub struct Amount(pub u64);
+
+impl Amount {
+    pub fn from_sat(sats: u64) -> Self {
+        Amount(sats)
+    }
+
+    pub fn to_sat(self) -> u64 {
+        self.0
+    }
+
+    // Parses e.g. "0.00012345" with integer math so no satoshis are lost to rounding
+    pub fn from_btc_str(s: &str) -> Result<Amount, BitcoinError> {
+        let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty()
+            || (s.contains('.') && fraction.is_empty())
+            || fraction.len() > 8
+            || !is_digits(whole)
+            || !is_digits(fraction)
+        {
+            return Err(BitcoinError::InvalidFormat);
+        }
+
+        let whole: u64 = whole.parse().map_err(|_| BitcoinError::InvalidFormat)?;
+        let fraction: u64 = format!("{fraction:0<8}")
+            .parse()
+            .map_err(|_| BitcoinError::InvalidFormat)?;
+        whole
+            .checked_mul(SATS_PER_BTC)
+            .and_then(|sats| sats.checked_add(fraction))
+            .map(Amount)
+            .ok_or(BitcoinError::InvalidFormat)
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+}
+
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+pub const DEFAULT_BYTES_PER_SIGOP: usize = 20;
+
+pub const TRUC_VERSION: u32 = 3;
+pub const TRUC_MAX_VSIZE: usize = 10_000;
+pub const TRUC_CHILD_MAX_VSIZE: usize = 1_000;
+pub const TRUC_ANCESTOR_LIMIT: usize = 1;
+
+// Version, empty input count and lock time
+pub const MIN_TX_SIZE: usize = 9;
+
+// Parses a block's transaction section (count plus transactions) without copying scripts
+pub fn parse_block_views(bytes: &[u8]) -> Result<Vec<TransactionView<'_>>, BitcoinError> {
+    let (txs, consumed) = read_transactions(bytes, TransactionView::from_bytes)?;
+    if consumed != bytes.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(txs)
+}
+
+// Owned counterpart of parse_block_views for callers that handle the header themselves
+pub fn parse_block_body(bytes: &[u8]) -> Result<(Vec<BitcoinTransaction>, usize), BitcoinError> {
+    read_transactions(bytes, BitcoinTransaction::from_bytes)
+}
+
+fn read_transactions<'a, T>(
+    bytes: &'a [u8],
+    parse: impl Fn(&'a [u8]) -> Result<(T, usize), BitcoinError>,
+) -> Result<(Vec<T>, usize), BitcoinError> {
+    let (tx_count, mut cursor) = CompactSize::from_bytes(bytes)?;
+    // Never reserve more transactions than the remaining bytes could hold
+    if tx_count.value > ((bytes.len() - cursor) / MIN_TX_SIZE) as u64 {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    let mut txs = Vec::with_capacity(tx_count.value as usize);
+    for _ in 0..tx_count.value {
+        let (tx, consumed) = parse(&bytes[cursor..])?;
+        txs.push(tx);
+        cursor += consumed;
+    }
+    Ok((txs, cursor))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PrefilledTransaction {
+    // Absolute position in the block, strictly increasing across the list;
+    // differential on the wire
+    pub index: usize,
+    pub tx: BitcoinTransaction,
+}
+
+// BIP152 cmpctblock payload. The header is kept as raw bytes since this
+// crate has no block header type.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CompactBlock {
+    pub header: [u8; 80],
+    pub nonce: u64,
+    pub short_ids: Vec<u64>,
+    pub prefilled: Vec<PrefilledTransaction>,
+}
+
+impl CompactBlock {
+    // Short ids must fit in 48 bits and prefilled indexes must be strictly increasing
+    // and fit in 16 bits, since that is all the wire format can express
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BitcoinError> {
+        let mut bytes = self.header.to_vec();
+        bytes.extend_from_slice(&self.nonce.to_le_bytes());
+
+        bytes.extend(CompactSize::new(self.short_ids.len() as u64).to_bytes());
+        for short_id in &self.short_ids {
+            if *short_id > 0x0000_FFFF_FFFF_FFFF {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            bytes.extend_from_slice(&short_id.to_le_bytes()[..6]);
+        }
+
+        bytes.extend(CompactSize::new(self.prefilled.len() as u64).to_bytes());
+        let mut next_index = 0;
+        for prefilled in &self.prefilled {
+            if prefilled.index < next_index || prefilled.index > u16::MAX as usize {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            let delta = prefilled.index - next_index;
+            bytes.extend(CompactSize::new(delta as u64).to_bytes());
+            bytes.extend(prefilled.tx.to_bytes());
+            next_index = prefilled.index + 1;
+        }
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let header = read_array(bytes, 0)?;
+        let nonce = u64::from_le_bytes(read_array(bytes, 80)?);
+        let mut cursor = 88;
+
+        let (short_id_count, consumed) = CompactSize::from_bytes(&bytes[cursor..])?;
+        cursor += consumed;
+        if short_id_count.value > ((bytes.len() - cursor) / 6) as u64 {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let mut short_ids = Vec::with_capacity(short_id_count.value as usize);
+        for _ in 0..short_id_count.value {
+            let mut id = [0u8; 8];
+            id[..6].copy_from_slice(&read_array::<6>(bytes, cursor)?);
+            short_ids.push(u64::from_le_bytes(id));
+            cursor += 6;
+        }
+
+        let (prefilled_count, consumed) = CompactSize::from_bytes(&bytes[cursor..])?;
+        cursor += consumed;
+        let mut prefilled = Vec::new();
+        let mut next_index: u64 = 0;
+        for _ in 0..prefilled_count.value {
+            let (delta, consumed) = CompactSize::from_bytes(&bytes[cursor..])?;
+            cursor += consumed;
+            // Core rejects indexes that do not fit in 16 bits
+            let index = next_index
+                .checked_add(delta.value)
+                .filter(|index| *index <= u16::MAX as u64)
+                .ok_or(BitcoinError::InvalidFormat)?;
+            let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes[cursor..])?;
+            cursor += consumed;
+            prefilled.push(PrefilledTransaction {
+                index: index as usize,
+                tx,
+            });
+            next_index = index + 1;
+        }
+
+        Ok((
+            CompactBlock {
+                header,
+                nonce,
+                short_ids,
+                prefilled,
+            },
+            cursor,
+        ))
+    }
+
+    // Total transactions in the block, short ids plus prefilled
+    pub fn transaction_count(&self) -> usize {
+        self.short_ids.len() + self.prefilled.len()
+    }
+}
+
+pub fn serialize_block_transactions(
+    txs: &[BitcoinTransaction],
+) -> (Vec<u8>, Vec<std::ops::Range<usize>>) {
+    let mut bytes = Vec::new();
+    let mut ranges = Vec::with_capacity(txs.len());
+    for tx in txs {
+        let start = bytes.len();
+        bytes.extend(tx.to_bytes());
+        ranges.push(start..bytes.len());
+    }
+    (bytes, ranges)
+}
+
+// Consensus caps transaction weight at 4,000,000, i.e. 1,000,000 bytes without witness data
+pub const MAX_TX_SIZE: usize = 1_000_000;
+// Outpoint, one-byte empty script length and sequence
+pub const MIN_INPUT_SIZE: usize = 41;
+pub const MAX_BLOCK_WEIGHT: usize = 4_000_000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ParseOptions {
+    pub require_canonical_compact_size: bool,
+    pub enforce_size_limits: bool,
+    pub reject_trailing_bytes: bool,
+    // Overall budget of bytes the parser may consume, checked as it goes
+    pub max_total_size: usize,
+}
+
+impl ParseOptions {
+    pub fn strict() -> Self {
+        ParseOptions {
+            require_canonical_compact_size: true,
+            enforce_size_limits: true,
+            reject_trailing_bytes: true,
+            max_total_size: MAX_BLOCK_WEIGHT,
+        }
+    }
+
+    pub fn permissive() -> Self {
+        ParseOptions {
+            require_canonical_compact_size: false,
+            enforce_size_limits: false,
+            reject_trailing_bytes: false,
+            max_total_size: MAX_BLOCK_WEIGHT,
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions::permissive()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TransactionInputView<'a> {
+    pub previous_output: OutPoint,
+    pub script_sig: ScriptRef<'a>,
+    pub sequence: u32,
+}
+
+impl<'a> TransactionInputView<'a> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(self.previous_output.to_bytes());
+        bytes.extend(self.script_sig.to_bytes());
+        bytes.extend_from_slice(&self.sequence.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, usize), BitcoinError> {
+        let mut cursor = 0;
+        let (previous_output, consumed) = OutPoint::from_bytes(&bytes[cursor..])?;
+        cursor += consumed;
+
+        let (script_sig, consumed) = Script::from_bytes_ref(&bytes[cursor..])?;
+        cursor += consumed;
+
+        let sequence = u32::from_le_bytes(read_array(bytes, cursor)?);
+        cursor += 4;
+
+        Ok((
+            TransactionInputView {
+                previous_output,
+                script_sig,
+                sequence,
+            },
+            cursor,
+        ))
+    }
+
+    pub fn into_owned(self) -> TransactionInput {
+        TransactionInput::new(
+            self.previous_output,
+            self.script_sig.to_script(),
+            self.sequence,
+        )
+    }
+}
+
+struct ParsedTransaction<I> {
+    version: u32,
+    inputs: Vec<I>,
+    lock_time: u32,
+    consumed: usize,
+    canonical: bool,
+}
+
+// Shared by the borrowed and owned parsers; `map_input` turns each borrowed input into
+// whatever the caller stores, so owned parsing copies each script once and builds a
+// single input vector
+fn parse_transaction<'a, I>(
+    bytes: &'a [u8],
+    options: &ParseOptions,
+    mut map_input: impl FnMut(TransactionInputView<'a>) -> I,
+) -> Result<ParsedTransaction<I>, BitcoinError> {
+    let mut cursor = 0;
+    let mut canonical = true;
+
+    // Read version (4 bytes LE)
+    let version = u32::from_le_bytes(read_array(bytes, cursor)?);
+    cursor += 4;
+
+    // Read input count
+    let (input_count, consumed) = CompactSize::from_bytes(&bytes[cursor..])?;
+    canonical &= consumed == input_count.encoding_width();
+    if options.require_canonical_compact_size && !canonical {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    if options.enforce_size_limits && input_count.value > (MAX_TX_SIZE / MIN_INPUT_SIZE) as u64 {
+        return Err(BitcoinError::SizeExceeded);
+    }
+    cursor += consumed;
+
+    // Read inputs
+    // Reserve only what the remaining bytes could hold; a hostile count just grows
+    let max_inputs = ((bytes.len() - cursor) / MIN_INPUT_SIZE) as u64;
+    let mut inputs = Vec::with_capacity(input_count.value.min(max_inputs) as usize);
+    for _ in 0..input_count.value {
+        let (input, consumed) = TransactionInputView::from_bytes(&bytes[cursor..])?;
+        // Whatever is not outpoint, sequence or script data is the length prefix
+        let script_prefix = consumed - 36 - 4 - input.script_sig.len();
+        canonical &=
+            script_prefix == CompactSize::new(input.script_sig.len() as u64).encoding_width();
+        if options.require_canonical_compact_size && !canonical {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        inputs.push(map_input(input));
+        cursor += consumed;
+        if cursor > options.max_total_size {
+            return Err(BitcoinError::SizeExceeded);
+        }
+    }
+
+    // Read lock_time
+    let lock_time = u32::from_le_bytes(read_array(bytes, cursor)?);
+    cursor += 4;
+    if cursor > options.max_total_size {
+        return Err(BitcoinError::SizeExceeded);
+    }
+
+    if options.enforce_size_limits && cursor > MAX_TX_SIZE {
+        return Err(BitcoinError::SizeExceeded);
+    }
+    if options.reject_trailing_bytes && cursor != bytes.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+
+    Ok(ParsedTransaction {
+        version,
+        inputs,
+        lock_time,
+        consumed: cursor,
+        canonical,
+    })
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TransactionView<'a> {
+    pub version: u32,
+    pub inputs: Vec<TransactionInputView<'a>>,
+    pub lock_time: u32,
+}
+
+impl<'a> TransactionView<'a> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend(CompactSize::new(self.inputs.len() as u64).to_bytes());
+        for input in &self.inputs {
+            bytes.extend(input.to_bytes());
+        }
+        bytes.extend_from_slice(&self.lock_time.to_le_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, usize), BitcoinError> {
+        TransactionView::from_bytes_with(bytes, &ParseOptions::permissive())
+    }
+
+    pub fn from_bytes_with(
+        bytes: &'a [u8],
+        options: &ParseOptions,
+    ) -> Result<(Self, usize), BitcoinError> {
+        let (tx, consumed, _) = TransactionView::parse(bytes, options)?;
+        Ok((tx, consumed))
+    }
+
+    // Also reports whether every CompactSize used its minimal encoding
+    fn parse(bytes: &'a [u8], options: &ParseOptions) -> Result<(Self, usize, bool), BitcoinError> {
+        let parsed = parse_transaction(bytes, options, |input| input)?;
+        Ok((
+            TransactionView {
+                version: parsed.version,
+                inputs: parsed.inputs,
+                lock_time: parsed.lock_time,
+            },
+            parsed.consumed,
+            parsed.canonical,
+        ))
+    }
+
+    pub fn into_owned(self) -> BitcoinTransaction {
+        BitcoinTransaction::new(
+            self.version,
+            self.inputs
+                .into_iter()
+                .map(TransactionInputView::into_owned)
+                .collect(),
+            self.lock_time,
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PartialTransaction {
+    pub version: Option<u32>,
+    pub input_count: Option<u64>,
+    // Only the inputs that parsed completely
+    pub inputs: Vec<TransactionInput>,
+    pub lock_time: Option<u32>,
+}
+
+impl PartialTransaction {
+    pub fn is_complete(&self) -> bool {
+        self.lock_time.is_some()
+    }
+
+    pub fn into_transaction(self) -> Option<BitcoinTransaction> {
+        Some(BitcoinTransaction::new(
+            self.version?,
+            self.inputs,
+            self.lock_time?,
+        ))
+    }
+}
+
+pub struct TxStream<R: Read> {
+    reader: R,
+    done: bool,
+}
+
+impl<R: Read> TxStream<R> {
+    pub fn new(reader: R) -> Self {
+        TxStream {
+            reader,
+            done: false,
+        }
+    }
+
+    fn read_next(&mut self) -> Result<Option<BitcoinTransaction>, BitcoinError> {
+        let mut len_bytes = [0u8; 4];
+        let read = read_fully(&mut self.reader, &mut len_bytes)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read < len_bytes.len() {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+
+        let len = u32::from_le_bytes(len_bytes) as usize;
+        // Grow with the data actually read rather than trusting the prefix
+        let mut bytes = Vec::new();
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut bytes)
+            .map_err(|e| BitcoinError::Io(e.kind()))?;
+        if bytes.len() < len {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+
+        let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes)?;
+        if consumed != len {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(Some(tx))
+    }
+}
+
+impl<R: Read> Iterator for TxStream<R> {
+    type Item = Result<BitcoinTransaction, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_next();
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result.transpose()
+    }
+}
+
+fn read_fully<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, BitcoinError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(BitcoinError::Io(e.kind())),
+        }
+    }
+    Ok(filled)
+}
+
+pub const SEQUENCE_FINAL: u32 = 0xFFFFFFFF;
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+pub const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000FFFF;
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum AbsoluteLock {
+    None,
+    Height(u32),
+    Time(u32),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct TimelockSummary {
+    pub absolute: AbsoluteLock,
+    // Smallest BIP68 relative lock among the inputs, split by lock type
+    pub min_relative_blocks: Option<u16>,
+    pub min_relative_seconds: Option<u32>,
+}
+
+impl TimelockSummary {
+    pub fn is_locked(&self) -> bool {
+        self.absolute != AbsoluteLock::None
+            || self.min_relative_blocks.is_some()
+            || self.min_relative_seconds.is_some()
     }
 }
 
@@ -300,14 +2056,10 @@ impl fmt::Display for BitcoinTransaction {
 
         for (i, input) in self.inputs.iter().enumerate() {
             writeln!(f, "Input {}", i)?;
-            writeln!(
-                f,
-                "  Previous Output Txid: {:?}",
-                input.previous_output.txid
-            )?;
+            writeln!(f, "  Previous Output Txid: {}", input.previous_output.txid)?;
             writeln!(f, "  Previous Output Vout: {}", input.previous_output.vout)?;
             writeln!(f, "  ScriptSig Length: {}", input.script_sig.bytes.len())?;
-            writeln!(f, "  ScriptSig: {:?}", input.script_sig.bytes)?;
+            writeln!(f, "  ScriptSig: {}", hex::encode(&input.script_sig.bytes))?;
             writeln!(f, "  Sequence: {:#x}", input.sequence)?;
         }
 
@@ -0,0 +1,46 @@
+use crate::{BitcoinTransaction, CompactSize, OutPoint, Script, TransactionInput, Txid};
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+pub fn compact_size() -> impl Strategy<Value = CompactSize> {
+    // Weight the encoding boundaries so every width gets exercised
+    prop_oneof![
+        any::<u64>(),
+        prop::sample::select(vec![
+            0u64,
+            252,
+            253,
+            65535,
+            65536,
+            4294967295,
+            4294967296,
+            u64::MAX,
+        ]),
+    ]
+    .prop_map(CompactSize::new)
+}
+
+pub fn txid() -> impl Strategy<Value = Txid> {
+    any::<[u8; 32]>().prop_map(Txid)
+}
+
+pub fn outpoint() -> impl Strategy<Value = OutPoint> {
+    (txid(), any::<u32>()).prop_map(|(txid, vout)| OutPoint { txid, vout })
+}
+
+pub fn script() -> impl Strategy<Value = Script> {
+    // Long enough to cross the 0xFD length prefix
+    vec(any::<u8>(), 0..300).prop_map(Script::new)
+}
+
+pub fn transaction_input() -> impl Strategy<Value = TransactionInput> {
+    (outpoint(), script(), any::<u32>()).prop_map(|(previous_output, script_sig, sequence)| {
+        TransactionInput::new(previous_output, script_sig, sequence)
+    })
+}
+
+pub fn bitcoin_transaction() -> impl Strategy<Value = BitcoinTransaction> {
+    (any::<u32>(), vec(transaction_input(), 0..4), any::<u32>()).prop_map(
+        |(version, inputs, lock_time)| BitcoinTransaction::new(version, inputs, lock_time),
+    )
+}
@@ -110,4 +110,1684 @@ mod tests {
         assert!(output.contains("Lock Time: 0"));
         assert!(output.contains("Previous Output Vout: 7"));
     }
+
+    #[test]
+    fn test_script_pubkeys() {
+        let mut compressed = vec![0x02];
+        compressed.extend([0x11; 32]);
+        let mut uncompressed = vec![0x04];
+        uncompressed.extend([0x22; 64]);
+        let mut not_a_key = vec![0x05];
+        not_a_key.extend([0x33; 32]);
+
+        let mut bytes = vec![0x51, 33];
+        bytes.extend(&compressed);
+        bytes.push(33);
+        bytes.extend(&not_a_key);
+        bytes.extend([OP_PUSHDATA1, 65]);
+        bytes.extend(&uncompressed);
+        bytes.extend([0x52, 0xAE]);
+
+        let script = Script::new(bytes);
+        assert_eq!(script.pubkeys(), vec![compressed, uncompressed]);
+
+        let truncated = Script::new(vec![0x4B, 0x02, 0x03]);
+        assert_eq!(
+            truncated.instructions().collect::<Vec<_>>(),
+            vec![Err(BitcoinError::InsufficientBytes)]
+        );
+    }
+
+    #[test]
+    fn test_parse_multisig() {
+        let key_a = [vec![0x02], vec![0xAA; 32]].concat();
+        let key_b = [vec![0x03], vec![0xBB; 32]].concat();
+
+        let mut bytes = vec![OP_1, 33];
+        bytes.extend(&key_a);
+        bytes.push(33);
+        bytes.extend(&key_b);
+        bytes.extend([OP_1 + 1, OP_CHECKMULTISIG]);
+
+        let info = Script::new(bytes.clone()).parse_multisig().unwrap();
+        assert_eq!(info.m, 1);
+        assert_eq!(info.n, 2);
+        assert_eq!(info.pubkeys, vec![key_a.clone(), key_b]);
+
+        // N does not match the number of pushed keys
+        let n_index = bytes.len() - 2;
+        bytes[n_index] = OP_1 + 2;
+        assert_eq!(Script::new(bytes).parse_multisig(), None);
+
+        // M greater than N
+        let mut bytes = vec![OP_1 + 1, 33];
+        bytes.extend(&key_a);
+        bytes.extend([OP_1, OP_CHECKMULTISIG]);
+        assert_eq!(Script::new(bytes).parse_multisig(), None);
+
+        assert_eq!(Script::new(vec![0x76, 0xA9]).parse_multisig(), None);
+    }
+
+    #[test]
+    fn test_with_all_sequences() {
+        let inputs = vec![
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0),
+            TransactionInput::new(OutPoint::new(dummy_txid(2), 1), Script::new(vec![]), 5),
+        ];
+        let tx = BitcoinTransaction::new(2, inputs, 0);
+        let rewritten = tx.with_all_sequences(0xFFFFFFFD);
+
+        assert!(rewritten.inputs.iter().all(|i| i.sequence == 0xFFFFFFFD));
+        assert_eq!(tx.inputs[1].sequence, 5);
+        assert_eq!(
+            rewritten.inputs[1].previous_output,
+            tx.inputs[1].previous_output
+        );
+    }
+
+    #[test]
+    fn test_zero_input_tx_roundtrip() {
+        let tx = BitcoinTransaction::new(1, vec![], 0);
+        let bytes = tx.to_bytes();
+        // The 0x00 input count directly follows the version
+        assert_eq!(
+            bytes,
+            vec![0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(consumed, bytes.len());
+    }
+
+    #[test]
+    fn test_from_hex_tolerates_prefix_and_whitespace() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(9), 1),
+                Script::new(vec![0xAB, 0xCD]),
+                0xFFFFFFFF,
+            )],
+            42,
+        );
+        let hex = tx.to_hex();
+        assert_eq!(BitcoinTransaction::from_hex(&hex).unwrap(), tx);
+
+        let (head, tail) = hex.split_at(20);
+        let pasted = format!("  0x{}\n\t{} \r\n", head, tail);
+        assert_eq!(BitcoinTransaction::from_hex(&pasted).unwrap(), tx);
+
+        assert_eq!(
+            BitcoinTransaction::from_hex("0xzz"),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_timelock_summary() {
+        let input = |sequence| {
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                sequence,
+            )
+        };
+
+        let final_tx = BitcoinTransaction::new(2, vec![input(0xFFFFFFFF)], 800_000);
+        let summary = final_tx.timelock_summary();
+        assert_eq!(summary.absolute, AbsoluteLock::None);
+        assert!(!summary.is_locked());
+
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![
+                input(144),
+                input(10),
+                input(SEQUENCE_LOCKTIME_TYPE_FLAG | 2),
+                input(SEQUENCE_LOCKTIME_DISABLE_FLAG | 1),
+            ],
+            800_000,
+        );
+        let summary = tx.timelock_summary();
+        assert_eq!(summary.absolute, AbsoluteLock::Height(800_000));
+        assert_eq!(summary.min_relative_blocks, Some(10));
+        assert_eq!(summary.min_relative_seconds, Some(1024));
+
+        let v1 = BitcoinTransaction::new(1, vec![input(10)], 1_700_000_000);
+        let summary = v1.timelock_summary();
+        assert_eq!(summary.absolute, AbsoluteLock::Time(1_700_000_000));
+        assert_eq!(summary.min_relative_blocks, None);
+    }
+
+    #[test]
+    fn test_compact_size_encoding_marker() {
+        let tests = vec![
+            (0u64, 0x00, 1),
+            (252, 0xFC, 1),
+            (253, 0xFD, 3),
+            (65536, 0xFE, 5),
+            (4294967296, 0xFF, 9),
+        ];
+        for (value, marker, width) in tests {
+            let cs = CompactSize::new(value);
+            assert_eq!(cs.encoding_marker(), marker);
+            assert_eq!(cs.encoding_width(), width);
+            assert_eq!(cs.to_bytes()[0], marker);
+            assert_eq!(cs.to_bytes().len(), width);
+        }
+
+        assert_eq!(
+            format!("{:?}", CompactSize::new(253)),
+            "CompactSize { value: 253, marker: 0xfd, width: 3 }"
+        );
+    }
+
+    #[test]
+    fn test_serialize_block_transactions() {
+        let txs = vec![
+            BitcoinTransaction::new(1, vec![], 0),
+            BitcoinTransaction::new(
+                2,
+                vec![TransactionInput::new(
+                    OutPoint::new(dummy_txid(3), 0),
+                    Script::new(vec![0x51]),
+                    0xFFFFFFFF,
+                )],
+                7,
+            ),
+        ];
+        let (bytes, ranges) = serialize_block_transactions(&txs);
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[1].end, bytes.len());
+        for (tx, range) in txs.iter().zip(ranges) {
+            let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes[range.clone()]).unwrap();
+            assert_eq!(&parsed, tx);
+            assert_eq!(consumed, range.len());
+        }
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_tx_roundtrip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let seed: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+        let mut u = Unstructured::new(&seed);
+        while let Ok(tx) = BitcoinTransaction::arbitrary(&mut u) {
+            let bytes = tx.to_bytes();
+            let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+            assert_eq!(parsed, tx);
+            assert_eq!(consumed, bytes.len());
+        }
+    }
+
+    #[test]
+    fn test_short_txid() {
+        let k0 = 0x0706050403020100;
+        let k1 = 0x0F0E0D0C0B0A0908;
+        assert_eq!(siphash24(k0, k1, &[]), 0x726fdb47dd0e0e31);
+        assert_eq!(siphash24(k0, k1, &[0x00]), 0x74f839c593dc67fd);
+
+        let mut wtxid = [0u8; 32];
+        for (i, byte) in wtxid.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        assert_eq!(siphash24(k0, k1, &wtxid), 0x7127512f72f27cce);
+        assert_eq!(short_txid(&Txid(wtxid), k0, k1), 0x512f72f27cce);
+    }
+
+    #[test]
+    fn test_script_from_bytes_ref() {
+        let script = Script::new(vec![0x76, 0xA9, 0x14, 0x88, 0xAC]);
+        let mut bytes = script.to_bytes();
+        bytes.push(0xFF);
+
+        let (borrowed, consumed) = Script::from_bytes_ref(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len() - 1);
+        assert_eq!(borrowed.0, &bytes[1..consumed]);
+        assert_eq!(borrowed.to_bytes(), script.to_bytes());
+        assert_eq!(borrowed.to_script(), script);
+        assert_eq!(borrowed, script.as_script_ref());
+
+        assert_eq!(
+            Script::from_bytes_ref(&[0x05, 0x01]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_transaction_view() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(
+                    OutPoint::new(dummy_txid(1), 0),
+                    Script::new(vec![0xAA; 40]),
+                    0xFFFFFFFF,
+                ),
+                TransactionInput::new(OutPoint::new(dummy_txid(2), 3), Script::new(vec![]), 1),
+            ],
+            500,
+        );
+        let bytes = tx.to_bytes();
+        let (view, consumed) = TransactionView::from_bytes(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(view.version, 2);
+        assert_eq!(
+            view.inputs[0].script_sig.0,
+            &tx.inputs[0].script_sig.bytes[..]
+        );
+        // The borrowed script points into the input buffer
+        assert!(bytes
+            .as_ptr_range()
+            .contains(&view.inputs[0].script_sig.0.as_ptr()));
+        assert_eq!(view.to_bytes(), bytes);
+        assert_eq!(view.into_owned(), tx);
+    }
+
+    #[test]
+    fn test_script_is_push_only() {
+        let mut script_sig = vec![OP_0, 0x02, 0x30, 0x44];
+        script_sig.extend([OP_PUSHDATA1, 0x01, 0xFF, OP_16]);
+        assert!(Script::new(script_sig).is_push_only());
+        assert!(Script::new(vec![]).is_push_only());
+
+        // OP_DUP is not a push
+        assert!(!Script::new(vec![0x01, 0xAA, 0x76]).is_push_only());
+        // A truncated push is not valid push-only data
+        assert!(!Script::new(vec![0x05, 0x01]).is_push_only());
+    }
+
+    #[test]
+    fn test_p2wpkh_script_code() {
+        let pubkey_hash = [0x5A; 20];
+        let program = Script::new([vec![OP_0, 0x14], pubkey_hash.to_vec()].concat());
+        let script_code = program.p2wpkh_script_code().unwrap();
+
+        let mut expected = vec![OP_DUP, OP_HASH160, 0x14];
+        expected.extend(pubkey_hash);
+        expected.extend([OP_EQUALVERIFY, OP_CHECKSIG]);
+        assert_eq!(script_code.bytes, expected);
+
+        // P2WSH programs (32 bytes) and non-v0 programs have no P2WPKH scriptCode
+        let p2wsh = Script::new([vec![OP_0, 0x20], vec![0x11; 32]].concat());
+        assert_eq!(p2wsh.p2wpkh_script_code(), None);
+        let v1 = Script::new([vec![OP_1, 0x14], pubkey_hash.to_vec()].concat());
+        assert_eq!(v1.p2wpkh_script_code(), None);
+    }
+
+    #[test]
+    fn test_tx_stream() {
+        let txs = vec![
+            BitcoinTransaction::new(1, vec![], 0),
+            BitcoinTransaction::new(
+                2,
+                vec![TransactionInput::new(
+                    OutPoint::new(dummy_txid(4), 1),
+                    Script::new(vec![0x51]),
+                    0xFFFFFFFE,
+                )],
+                10,
+            ),
+        ];
+        let mut dump = Vec::new();
+        for tx in &txs {
+            let bytes = tx.to_bytes();
+            dump.extend((bytes.len() as u32).to_le_bytes());
+            dump.extend(bytes);
+        }
+
+        let parsed: Vec<_> = TxStream::new(&dump[..]).collect::<Result<_, _>>().unwrap();
+        assert_eq!(parsed, txs);
+
+        // A record cut short surfaces an error and ends the stream
+        let truncated = &dump[..dump.len() - 2];
+        let results: Vec<_> = TxStream::new(truncated).collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0], Ok(txs[0].clone()));
+        assert_eq!(results[1], Err(BitcoinError::InsufficientBytes));
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn test_json_schema() {
+        let schema = serde_json::to_value(json_schema()).unwrap();
+        assert_eq!(schema["title"], "BitcoinTransaction");
+        assert_eq!(schema["$defs"]["Txid"]["type"], "string");
+        assert_eq!(schema["$defs"]["Txid"]["pattern"], "^[0-9a-fA-F]{64}$");
+        assert!(schema["$defs"]["TransactionInput"]["properties"]["sequence"].is_object());
+    }
+
+    #[test]
+    fn test_from_bytes_partial() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![1]), 7),
+                TransactionInput::new(OutPoint::new(dummy_txid(2), 1), Script::new(vec![2]), 8),
+            ],
+            99,
+        );
+        let bytes = tx.to_bytes();
+
+        let (partial, consumed) = BitcoinTransaction::from_bytes_partial(&bytes);
+        assert!(partial.is_complete());
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(partial.into_transaction(), Some(tx.clone()));
+
+        // Cut in the middle of the second input
+        let second_input_start = 4 + 1 + tx.inputs[0].to_bytes().len();
+        let (partial, consumed) =
+            BitcoinTransaction::from_bytes_partial(&bytes[..second_input_start + 10]);
+        assert!(!partial.is_complete());
+        assert_eq!(consumed, second_input_start);
+        assert_eq!(partial.version, Some(2));
+        assert_eq!(partial.input_count, Some(2));
+        assert_eq!(partial.inputs, vec![tx.inputs[0].clone()]);
+        assert_eq!(partial.lock_time, None);
+
+        let (partial, consumed) = BitcoinTransaction::from_bytes_partial(&bytes[..2]);
+        assert_eq!(partial, PartialTransaction::default());
+        assert_eq!(consumed, 0);
+    }
+
+    #[test]
+    fn test_bitcoin_transaction_display_formats() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(0xCD), 7),
+            Script::new(vec![0xDE, 0xAD]),
+            10,
+        );
+        let tx = BitcoinTransaction::new(2, vec![input], 0);
+
+        let output = format!("{}", tx);
+        assert!(output.contains(&format!("Previous Output Txid: cd{}", "00".repeat(31))));
+        assert!(output.contains("ScriptSig: dead"));
+
+        assert_eq!(
+            tx.display_summary(),
+            "version 2, 1 input(s), 52 bytes, lock time 0"
+        );
+        let detailed = tx.display_detailed();
+        assert!(detailed.starts_with(&output));
+        assert!(detailed.contains("Size: 52 bytes"));
+        assert!(detailed.contains("Min Relative Lock: 10 blocks"));
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_normalized_txid() {
+        assert_eq!(
+            hex::encode(sha256d(b"hello")),
+            "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50"
+        );
+
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![0x01, 0xAA]),
+            0xFFFFFFFF,
+        );
+        let tx = BitcoinTransaction::new(1, vec![input], 0);
+
+        let mut malleated = tx.clone();
+        malleated.inputs[0].script_sig = Script::new(vec![0x4C, 0x01, 0xAA]);
+        assert_ne!(tx.to_bytes(), malleated.to_bytes());
+        assert_eq!(tx.normalized_txid(), malleated.normalized_txid());
+
+        let resequenced = tx.with_all_sequences(0);
+        assert_ne!(tx.normalized_txid(), resequenced.normalized_txid());
+    }
+
+    #[test]
+    fn test_legacy_sigop_count() {
+        let script = Script::new(vec![
+            OP_DUP,
+            OP_CHECKSIG,
+            OP_CHECKSIGVERIFY,
+            OP_CHECKMULTISIG,
+        ]);
+        assert_eq!(script.legacy_sigop_count(), 22);
+        // Opcode bytes inside a push are data, not operations
+        assert_eq!(
+            Script::new(vec![0x02, OP_CHECKSIG, OP_CHECKSIG]).legacy_sigop_count(),
+            0
+        );
+
+        let input = |bytes: Vec<u8>| {
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(bytes), 0)
+        };
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![
+                input(vec![OP_CHECKSIG]),
+                input(vec![OP_CHECKMULTISIGVERIFY]),
+            ],
+            0,
+        );
+        assert_eq!(tx.legacy_sigop_count(), 21);
+    }
+
+    #[test]
+    fn test_coinbase_input() {
+        let coinbase = TransactionInput::new(
+            OutPoint::null(),
+            Script::new(vec![0x03, 0x40, 0x0D, 0x03]),
+            0xFFFFFFFF,
+        );
+        let tx = BitcoinTransaction::new(1, vec![coinbase.clone()], 0);
+        assert!(tx.is_coinbase());
+        assert_eq!(tx.coinbase_input(), Some(&coinbase));
+
+        let spend = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        let tx = BitcoinTransaction::new(1, vec![spend], 0);
+        assert_eq!(tx.coinbase_input(), None);
+
+        let tx = BitcoinTransaction::new(1, vec![coinbase.clone(), coinbase], 0);
+        assert_eq!(tx.coinbase_input(), None);
+    }
+
+    #[test]
+    fn test_matches_bloom() {
+        // Core's bloom_create_insert_serialize vector
+        let mut filter = BloomFilter::new(3, 0.01, 0);
+        for element in [
+            "99108ad8ed9bb6274d3980bab5a85c048f0950c8",
+            "b5a2c786d9ef4658287ced5914b37a1b4aa32eee",
+            "b9300670b4c5366e95b2699e8b18bc75e5f729c5",
+        ] {
+            filter.insert(&hex::decode(element).unwrap());
+        }
+        assert_eq!(hex::encode(&filter.data), "614e9b");
+        assert_eq!(filter.hash_funcs, 5);
+        // Core divides bits by elements as integers: 80 / 9 = 8 and 32 / 7 = 4
+        assert_eq!(BloomFilter::new(9, 0.01, 0).hash_funcs, 5);
+        assert_eq!(BloomFilter::new(7, 0.1, 0).hash_funcs, 2);
+        assert!(filter.contains(&hex::decode("99108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()));
+        assert!(!filter.contains(&hex::decode("19108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()));
+
+        let pubkey = [vec![0x02], vec![0x77; 32]].concat();
+        let script_sig = Script::new([vec![33], pubkey.clone()].concat());
+        let outpoint = OutPoint::new(dummy_txid(5), 2);
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                outpoint.clone(),
+                script_sig,
+                0xFFFFFFFF,
+            )],
+            0,
+        );
+
+        let mut by_push = BloomFilter::new(10, 0.000001, 0);
+        by_push.insert(&pubkey);
+        assert!(tx.matches_bloom(&by_push));
+
+        let mut by_outpoint = BloomFilter::new(10, 0.000001, 0);
+        by_outpoint.insert(&outpoint.to_bytes());
+        assert!(tx.matches_bloom(&by_outpoint));
+
+        let mut unrelated = BloomFilter::new(10, 0.000001, 0);
+        unrelated.insert(b"nothing to see");
+        assert!(!tx.matches_bloom(&unrelated));
+
+        // An empty filter ignores inserts instead of dividing by zero
+        let mut empty = BloomFilter {
+            data: vec![],
+            hash_funcs: 5,
+            tweak: 0,
+        };
+        empty.insert(&pubkey);
+        assert!(empty.data.is_empty());
+        assert!(!tx.matches_bloom(&empty));
+    }
+
+    #[test]
+    fn test_murmur3_vectors() {
+        use rust_week_3_exercises::hashes::murmur3_32;
+
+        let vectors = [
+            (0x00000000, 0x00000000, ""),
+            (0x6a396f08, 0xFBA4C795, ""),
+            (0x81f16f39, 0xffffffff, ""),
+            (0x514e28b7, 0x00000000, "00"),
+            (0xea3f0b17, 0xFBA4C795, "00"),
+            (0xfd6cf10d, 0x00000000, "ff"),
+            (0x16c6b7ab, 0x00000000, "0011"),
+            (0x8eb51c3d, 0x00000000, "001122"),
+            (0xb4471bf8, 0x00000000, "00112233"),
+            (0xe2301fa8, 0x00000000, "0011223344"),
+            (0xfc2e4a15, 0x00000000, "001122334455"),
+            (0xb074502c, 0x00000000, "00112233445566"),
+            (0x8034d2a0, 0x00000000, "0011223344556677"),
+            (0xb4698def, 0x00000000, "001122334455667788"),
+        ];
+        for (expected, seed, data) in vectors {
+            assert_eq!(murmur3_32(seed, &hex::decode(data).unwrap()), expected);
+        }
+    }
+
+    #[test]
+    fn test_hashing_chunks() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![1, 2]), 3),
+                TransactionInput::new(OutPoint::new(dummy_txid(2), 1), Script::new(vec![]), 4),
+            ],
+            5,
+        );
+        let chunks = tx.hashing_chunks();
+        assert_eq!(chunks.len(), 2 + 3 * 2 + 1);
+        assert_eq!(chunks[0].kind, ChunkKind::Version);
+        assert_eq!(chunks[3].kind, ChunkKind::ScriptSig(0));
+        assert_eq!(chunks[3].bytes, vec![0x02, 0x01, 0x02]);
+        assert_eq!(chunks[7].kind, ChunkKind::Sequence(1));
+        assert_eq!(chunks[8].kind, ChunkKind::LockTime);
+
+        let joined: Vec<u8> = chunks.into_iter().flat_map(|c| c.bytes).collect();
+        assert_eq!(joined, tx.to_bytes());
+    }
+
+    #[test]
+    fn test_from_bytes_with_options() {
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0xAA]),
+                0,
+            )],
+            0,
+        );
+        let bytes = tx.to_bytes();
+        let strict = ParseOptions::strict();
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with(&bytes, &strict).unwrap(),
+            (tx.clone(), bytes.len())
+        );
+
+        // Input count 1 encoded as 0xFD 0x01 0x00
+        let mut non_canonical = bytes[..4].to_vec();
+        non_canonical.extend([0xFD, 0x01, 0x00]);
+        non_canonical.extend(&bytes[5..]);
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with(&non_canonical, &strict),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&non_canonical).unwrap().0,
+            tx
+        );
+
+        // Script length 1 encoded as 0xFD 0x01 0x00
+        let mut long_script_len = bytes[..41].to_vec();
+        long_script_len.extend([0xFD, 0x01, 0x00]);
+        long_script_len.extend(&bytes[42..]);
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&long_script_len).unwrap().0,
+            tx
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with(&long_script_len, &strict),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.push(0x00);
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with(&trailing, &strict),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert!(
+            BitcoinTransaction::from_bytes_with(&trailing, &ParseOptions::permissive()).is_ok()
+        );
+
+        let mut huge_count = bytes[..4].to_vec();
+        huge_count.extend([0xFE, 0xFF, 0xFF, 0xFF, 0x00]);
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with(&huge_count, &strict),
+            Err(BitcoinError::SizeExceeded)
+        );
+    }
+
+    #[test]
+    fn test_required_fee() {
+        let input =
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![0; 13]), 0);
+        let tx = BitcoinTransaction::new(1, vec![input], 0);
+        // 4 + 1 + 36 + 1 + 13 + 4 + 4
+        assert_eq!(tx.vsize(), 63);
+        assert_eq!(tx.weight(), 252);
+
+        assert_eq!(tx.required_fee(1.0), 63);
+        assert_eq!(tx.required_fee(1.5), 95); // 94.5 rounds up
+        assert_eq!(tx.required_fee(0.001), 1); // 0.063 rounds up
+        assert_eq!(tx.required_fee(0.0004), 1); // 0.0252 rounds up
+        assert_eq!(tx.required_fee(1.0004), 64); // 63.0252 rounds up
+        assert_eq!(tx.required_fee(0.0), 0);
+        assert_eq!(tx.required_fee(-2.0), 0);
+        assert_eq!(tx.required_fee(f64::NAN), 0);
+        assert_eq!(tx.required_fee(f64::INFINITY), 0);
+
+        // 1.1 * 100 is 110.00000000000001 in floating point
+        let padded = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0; 50]),
+                0,
+            )],
+            0,
+        );
+        assert_eq!(padded.vsize(), 100);
+        assert_eq!(padded.required_fee(1.1), 110);
+
+        // The rate is applied exactly, not rounded to whole sat/kvB first
+        let large = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0; 99_946]),
+                0,
+            )],
+            0,
+        );
+        assert_eq!(large.vsize(), 100_000);
+        assert_eq!(large.required_fee(1.0001), 100_010);
+        assert_eq!(large.required_fee(0.0004), 40);
+    }
+
+    #[test]
+    fn test_nested_witness_program() {
+        let redeem_script = [vec![OP_0, 0x14], vec![0x42; 20]].concat();
+        let script_sig =
+            Script::new([vec![redeem_script.len() as u8], redeem_script.clone()].concat());
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), script_sig, 0xFFFFFFFF);
+        assert_eq!(input.nested_witness_program(), Some((0, vec![0x42; 20])));
+
+        assert_eq!(
+            Script::new(redeem_script.clone()).witness_program(),
+            Some((0, &[0x42; 20][..]))
+        );
+        let taproot = Script::new([vec![OP_1, 0x20], vec![0x07; 32]].concat());
+        assert_eq!(taproot.witness_program(), Some((1, &[0x07; 32][..])));
+
+        // Extra data after the redeem script push
+        let mut extra = input.clone();
+        extra.script_sig.bytes.push(OP_0);
+        assert_eq!(extra.nested_witness_program(), None);
+
+        // A pushed P2PKH-style script is not a witness program
+        let legacy = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![0x03, OP_DUP, OP_HASH160, 0x00]),
+            0,
+        );
+        assert_eq!(legacy.nested_witness_program(), None);
+    }
+
+    #[test]
+    fn test_read_array() {
+        let bytes = [1u8, 2, 3, 4, 5];
+        assert_eq!(read_array::<4>(&bytes, 1), Ok([2, 3, 4, 5]));
+        assert_eq!(read_array::<0>(&bytes, 5), Ok([]));
+        assert_eq!(
+            read_array::<4>(&bytes, 2),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(
+            read_array::<32>(&bytes, 0),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(
+            read_array::<1>(&bytes, usize::MAX),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_with_canonicality() {
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0xAA]),
+                0,
+            )],
+            0,
+        );
+        let bytes = tx.to_bytes();
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_canonicality(&bytes).unwrap(),
+            (tx.clone(), bytes.len(), true)
+        );
+
+        // Script length 1 encoded with the 0xFE form
+        let mut non_canonical = bytes[..41].to_vec();
+        non_canonical.extend([0xFE, 0x01, 0x00, 0x00, 0x00]);
+        non_canonical.extend(&bytes[42..]);
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_canonicality(&non_canonical).unwrap(),
+            (tx, non_canonical.len(), false)
+        );
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_roundtrip() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(7), 1),
+                Script::new(vec![0xFB; 3]),
+                0,
+            )],
+            0,
+        );
+        let encoded = tx.to_base64();
+        assert!(encoded.starts_with("AgAAAAE"));
+        assert_eq!(BitcoinTransaction::from_base64(&encoded).unwrap(), tx);
+        assert_eq!(
+            BitcoinTransaction::from_base64("not base64!"),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_estimate_vsize() {
+        let p2wpkh_out = Script::new([vec![OP_0, 0x14], vec![0; 20]].concat());
+        let p2pkh_out = Script::new(
+            [
+                vec![OP_DUP, OP_HASH160, 0x14],
+                vec![0; 20],
+                vec![OP_EQUALVERIFY, OP_CHECKSIG],
+            ]
+            .concat(),
+        );
+        let one_out = vec![p2wpkh_out.clone()];
+        let two_out = vec![p2wpkh_out.clone(), p2wpkh_out];
+
+        // 1-in 2-out P2WPKH: 4 + 1 + 41 + 1 + 2 * 31 + 4 = 113 base, 2 + 109 witness
+        assert_eq!(estimate_vsize(&[InputType::P2wpkh], &two_out), 141);
+        // 1-in 1-out P2PKH: 4 + 1 + (36 + 1 + 108 + 4) + 1 + 34 + 4, no witness
+        assert_eq!(estimate_vsize(&[InputType::P2pkh], &[p2pkh_out]), 193);
+        // Key-path taproot spend: 4 + 1 + 41 + 1 + 31 + 4 = 82 base, 2 + 67 witness
+        assert_eq!(estimate_vsize(&[InputType::P2trKeypath], &one_out), 100);
+
+        // Mixed: the legacy input contributes an empty witness stack
+        let mixed = estimate_vsize(&[InputType::P2pkh, InputType::P2wpkh], &one_out);
+        let legacy_only = estimate_vsize(&[InputType::P2pkh], &one_out);
+        assert!(mixed > legacy_only);
+
+        assert_eq!(
+            InputType::P2wshMultisig { m: 2, n: 3 }.witness_size(),
+            1 + 1 + 2 * 72 + 1 + 105
+        );
+        assert_eq!(InputType::P2pkh.max_signature_size(), (108, 0));
+        assert_eq!(InputType::P2wpkh.max_signature_size(), (0, 109));
+        assert_eq!(
+            InputType::P2wshMultisig { m: 2, n: 3 }.max_signature_size(),
+            (0, 1 + 1 + 2 * 74 + 1 + 105)
+        );
+    }
+
+    #[test]
+    fn test_var_bytes() {
+        use rust_week_3_exercises::encoding::{read_var_bytes, write_var_bytes};
+
+        let data = vec![0xAB; 300];
+        let bytes = write_var_bytes(&data);
+        assert_eq!(&bytes[..3], &[0xFD, 0x2C, 0x01]);
+        assert_eq!(read_var_bytes(&bytes).unwrap(), (data, 303));
+
+        assert_eq!(read_var_bytes(&[0x00]).unwrap(), (vec![], 1));
+        assert_eq!(
+            read_var_bytes(&[0x03, 0x01, 0x02]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        // Declared length larger than any buffer
+        let huge = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert_eq!(read_var_bytes(&huge), Err(BitcoinError::InsufficientBytes));
+    }
+
+    #[test]
+    fn test_explain() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(vec![]),
+            0xFFFFFFFD,
+        );
+        let tx = BitcoinTransaction::new(2, vec![input.clone(), input], 800_000);
+        assert!(tx.signals_rbf());
+        assert_eq!(
+            tx.explain(),
+            "This version 2 transaction spends 2 inputs. It signals RBF: yes. \
+             It cannot be mined before block 800000. Is SegWit: no. Weight: 364 WU (91 vbytes)."
+        );
+
+        let final_tx = tx.with_all_sequences(0xFFFFFFFF);
+        assert!(!final_tx.signals_rbf());
+        assert!(final_tx
+            .explain()
+            .contains("It signals RBF: no. It has no absolute lock time."));
+    }
+
+    #[test]
+    fn test_compact_block_roundtrip() {
+        let coinbase = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::null(),
+                Script::new(vec![0x01, 0x01]),
+                0xFFFFFFFF,
+            )],
+            0,
+        );
+        let block = CompactBlock {
+            header: [0x11; 80],
+            nonce: 0x0102030405060708,
+            short_ids: vec![0x0000_AABB_CCDD_EEFF, 0x0000_0000_0000_0001],
+            prefilled: vec![
+                PrefilledTransaction {
+                    index: 0,
+                    tx: coinbase.clone(),
+                },
+                PrefilledTransaction {
+                    index: 3,
+                    tx: coinbase,
+                },
+            ],
+        };
+        let bytes = block.to_bytes().unwrap();
+        let short_ids_start = 88 + 1;
+        assert_eq!(
+            &bytes[short_ids_start..short_ids_start + 6],
+            &[0xFF, 0xEE, 0xDD, 0xCC, 0xBB, 0xAA]
+        );
+
+        let (parsed, consumed) = CompactBlock::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, block);
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(parsed.transaction_count(), 4);
+
+        // A short id count that cannot fit in the remaining bytes
+        let mut bogus = bytes[..88].to_vec();
+        bogus.extend([0xFE, 0xFF, 0xFF, 0xFF, 0x00]);
+        assert_eq!(
+            CompactBlock::from_bytes(&bogus),
+            Err(BitcoinError::InsufficientBytes)
+        );
+
+        // Unordered or repeated prefilled indexes cannot be delta-encoded
+        let mut unordered = block.clone();
+        unordered.prefilled[0].index = 2;
+        unordered.prefilled[1].index = 1;
+        assert_eq!(unordered.to_bytes(), Err(BitcoinError::InvalidFormat));
+        let mut repeated = block.clone();
+        repeated.prefilled[1].index = 0;
+        assert_eq!(repeated.to_bytes(), Err(BitcoinError::InvalidFormat));
+
+        // Short ids are 6 bytes on the wire, so wider values are rejected
+        let mut wide = block;
+        wide.short_ids[0] = u64::MAX;
+        assert_eq!(wide.to_bytes(), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_prevouts_and_sequences_hash() {
+        // Inputs of the native P2WPKH example in BIP143
+        let outpoint = |txid: &str, vout| {
+            let mut bytes = [0u8; 32];
+            bytes.copy_from_slice(&hex::decode(txid).unwrap());
+            OutPoint::new(bytes, vout)
+        };
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![
+                TransactionInput::new(
+                    outpoint(
+                        "fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f",
+                        0,
+                    ),
+                    Script::new(vec![]),
+                    0xFFFFFFEE,
+                ),
+                TransactionInput::new(
+                    outpoint(
+                        "ef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a",
+                        1,
+                    ),
+                    Script::new(vec![]),
+                    0xFFFFFFFF,
+                ),
+            ],
+            0x11,
+        );
+        assert_eq!(
+            hex::encode(tx.prevouts_hash()),
+            "96b827c8483d4e9b96712b6713a7b68d6e8003a781feba36c31143470b4efd37"
+        );
+        assert_eq!(
+            hex::encode(tx.sequences_hash()),
+            "52b0a642eea2fb7ae638c36f6252b6750293dbe574a806984b8e4d8548339a3b"
+        );
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn test_dummy_transaction() {
+        let tx = BitcoinTransaction::dummy(3);
+        assert_eq!(tx.inputs.len(), 3);
+        assert!(tx
+            .inputs
+            .iter()
+            .all(|i| i.previous_output.is_null() && i.script_sig.is_empty()));
+        assert_eq!(tx.vsize(), 4 + 1 + 3 * 41 + 4);
+        assert_eq!(BitcoinTransaction::dummy(3), tx);
+    }
+
+    #[test]
+    fn test_parse_block_views() {
+        let txs = vec![
+            BitcoinTransaction::new(
+                1,
+                vec![TransactionInput::new(
+                    OutPoint::null(),
+                    Script::new(vec![0x51; 4]),
+                    0xFFFFFFFF,
+                )],
+                0,
+            ),
+            BitcoinTransaction::new(2, vec![], 0),
+        ];
+        let (tx_bytes, _) = serialize_block_transactions(&txs);
+        let mut body = CompactSize::new(2).to_bytes();
+        body.extend(&tx_bytes);
+
+        let views = parse_block_views(&body).unwrap();
+        assert_eq!(views.len(), 2);
+        assert_eq!(views[0].inputs[0].script_sig.0, &[0x51; 4][..]);
+        let owned: Vec<_> = views.into_iter().map(TransactionView::into_owned).collect();
+        assert_eq!(owned, txs);
+
+        let mut trailing = body.clone();
+        trailing.push(0);
+        assert_eq!(
+            parse_block_views(&trailing),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let huge_count = [0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x00];
+        assert_eq!(
+            parse_block_views(&huge_count),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_encode_to_slice() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(OutPoint::new([7; 32], 1), Script::new(vec![0xAB; 300]), 5),
+                TransactionInput::new(OutPoint::null(), Script::new(vec![]), 0xFFFFFFFF),
+            ],
+            99,
+        );
+        let expected = tx.to_bytes();
+        assert_eq!(tx.serialized_size(), expected.len());
+
+        let mut buf = [0u8; 512];
+        let written = tx.encode_to_slice(&mut buf).unwrap();
+        assert_eq!(&buf[..written], &expected[..]);
+
+        let mut short = vec![0u8; expected.len() - 1];
+        assert_eq!(
+            tx.encode_to_slice(&mut short),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_truc_policy() {
+        let input = TransactionInput::new(OutPoint::null(), Script::new(vec![]), 0);
+        let truc = BitcoinTransaction::new(3, vec![input.clone()], 0);
+        assert!(truc.is_truc());
+        assert_eq!(truc.check_truc_policy(1), Ok(()));
+        assert_eq!(truc.check_truc_policy(2), Err(BitcoinError::NonStandard));
+
+        let big = BitcoinTransaction::new(
+            3,
+            vec![TransactionInput::new(
+                OutPoint::null(),
+                Script::new(vec![0; 10_000]),
+                0,
+            )],
+            0,
+        );
+        assert_eq!(big.check_truc_policy(0), Err(BitcoinError::SizeExceeded));
+
+        // 5,052 vB is fine on its own but over the 1,000 vB limit for a child
+        let child = BitcoinTransaction::new(
+            3,
+            vec![TransactionInput::new(
+                OutPoint::null(),
+                Script::new(vec![0; 5_000]),
+                0,
+            )],
+            0,
+        );
+        assert_eq!(child.vsize(), 5_052);
+        assert_eq!(child.check_truc_policy(0), Ok(()));
+        assert_eq!(child.check_truc_policy(1), Err(BitcoinError::SizeExceeded));
+
+        let v2 = BitcoinTransaction::new(2, vec![input], 0);
+        assert!(!v2.is_truc());
+        assert_eq!(v2.check_truc_policy(5), Ok(()));
+    }
+
+    #[test]
+    fn test_all_scripts() {
+        let first = Script::new(vec![0x51]);
+        let second = Script::new(vec![0x52, 0x53]);
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![
+                TransactionInput::new(OutPoint::null(), first.clone(), 0),
+                TransactionInput::new(OutPoint::null(), second.clone(), 0),
+            ],
+            0,
+        );
+        assert_eq!(tx.all_scripts(), vec![&first, &second]);
+    }
+
+    #[test]
+    fn test_amount_from_btc_str() {
+        let sum = Amount::from_btc_str("0.1")
+            .unwrap()
+            .checked_add(Amount::from_btc_str("0.2").unwrap())
+            .unwrap();
+        assert_eq!(sum, Amount::from_btc_str("0.3").unwrap());
+        assert_eq!(sum.to_sat(), 30_000_000);
+
+        assert_eq!(
+            Amount::from_btc_str("21000000").unwrap().to_sat(),
+            2_100_000_000_000_000
+        );
+        assert_eq!(
+            Amount::from_btc_str("0.00000001").unwrap(),
+            Amount::from_sat(1)
+        );
+
+        for bad in [
+            "",
+            "1.",
+            ".5",
+            "0.000000001",
+            "1e3",
+            "-1",
+            "1.2.3",
+            "99999999999999999999",
+        ] {
+            assert_eq!(
+                Amount::from_btc_str(bad),
+                Err(BitcoinError::InvalidFormat),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_has_minimal_pushes() {
+        let minimal = Script::new(vec![OP_0, OP_1, 0x01, 0x11, 0x02, 0x01, 0x00, OP_DUP]);
+        assert!(minimal.has_minimal_pushes());
+
+        // 0x01 should be OP_1, 0x81 should be OP_1NEGATE
+        assert!(!Script::new(vec![0x01, 0x01]).has_minimal_pushes());
+        assert!(!Script::new(vec![0x01, 0x81]).has_minimal_pushes());
+        // Empty push via OP_PUSHDATA1 instead of OP_0
+        assert!(!Script::new(vec![OP_PUSHDATA1, 0x00]).has_minimal_pushes());
+
+        let mut pushdata1 = vec![OP_PUSHDATA1, 10];
+        pushdata1.extend([0xAA; 10]);
+        assert!(!Script::new(pushdata1).has_minimal_pushes());
+
+        let mut long = vec![OP_PUSHDATA1, 80];
+        long.extend([0xAA; 80]);
+        assert!(Script::new(long).has_minimal_pushes());
+
+        assert!(!Script::new(vec![0x05, 0x01]).has_minimal_pushes());
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_canonicalize() {
+        // Input count 1 and script length 2 encoded with 0xFD prefixes
+        let mut bytes = vec![1, 0, 0, 0, 0xFD, 0x01, 0x00];
+        bytes.extend([0x11; 32]);
+        bytes.extend([0, 0, 0, 0]);
+        bytes.extend([0xFD, 0x02, 0x00, 0x51, 0x52]);
+        bytes.extend([0xFF; 4]);
+        bytes.extend([0; 4]);
+
+        let (tx, _, canonical) = BitcoinTransaction::from_bytes_with_canonicality(&bytes).unwrap();
+        assert!(!canonical);
+
+        let repaired = tx.canonicalize();
+        assert_eq!(repaired.len(), bytes.len() - 4);
+        assert_ne!(sha256d(&repaired), sha256d(&bytes));
+        let (reparsed, _, canonical) =
+            BitcoinTransaction::from_bytes_with_canonicality(&repaired).unwrap();
+        assert!(canonical);
+        assert_eq!(reparsed, tx);
+        assert_eq!(reparsed.inputs[0].script_sig.bytes, vec![0x51, 0x52]);
+    }
+
+    #[test]
+    fn test_parse_block_body() {
+        let txs = vec![
+            BitcoinTransaction::new(1, vec![], 0),
+            BitcoinTransaction::new(
+                2,
+                vec![TransactionInput::new(
+                    OutPoint::new([3; 32], 0),
+                    Script::new(vec![0x51]),
+                    0,
+                )],
+                500,
+            ),
+        ];
+        let mut body = CompactSize::new(2).to_bytes();
+        body.extend(serialize_block_transactions(&txs).0);
+        let body_len = body.len();
+        body.extend([0xAA; 3]);
+
+        assert_eq!(parse_block_body(&body), Ok((txs, body_len)));
+        assert_eq!(
+            parse_block_body(&[0xFD, 0xFF, 0xFF, 0x00]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_sigop_adjusted_vsize() {
+        let plain = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::null(),
+                Script::new(vec![OP_CHECKSIG]),
+                0,
+            )],
+            0,
+        );
+        assert_eq!(plain.sigop_adjusted_vsize(), plain.vsize());
+
+        // 40 CHECKSIGs cost 40 * 4 * 20 = 3200 WU, more than the 90-byte size
+        let heavy = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::null(),
+                Script::new(vec![OP_CHECKSIG; 40]),
+                0,
+            )],
+            0,
+        );
+        assert_eq!(heavy.vsize(), 90);
+        assert_eq!(heavy.sigop_adjusted_vsize(), 800);
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_custom_sha256d_backend() {
+        struct Reversed;
+        impl Sha256d for Reversed {
+            fn sha256d(data: &[u8]) -> [u8; 32] {
+                let mut hash = Sha2::sha256d(data);
+                hash.reverse();
+                hash
+            }
+        }
+
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new([9; 32], 2),
+                Script::new(vec![]),
+                7,
+            )],
+            0,
+        );
+        assert_eq!(tx.prevouts_hash_with::<Sha2>(), tx.prevouts_hash());
+        let mut reversed = tx.sequences_hash();
+        reversed.reverse();
+        assert_eq!(tx.sequences_hash_with::<Reversed>(), reversed);
+        assert_eq!(Sha2::sha256d(b"hello"), sha256d(b"hello"));
+    }
+
+    #[test]
+    fn test_spent_outpoints_display() {
+        let mut txid = [0u8; 32];
+        txid[0] = 0xAB;
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![
+                TransactionInput::new(OutPoint::new(txid, 3), Script::new(vec![]), 0),
+                TransactionInput::new(OutPoint::null(), Script::new(vec![]), 0),
+            ],
+            0,
+        );
+        assert_eq!(
+            tx.spent_outpoints_display(),
+            vec![
+                format!("{}ab:3", "0".repeat(62)),
+                format!("{}:4294967295", "0".repeat(64)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_sighash_preimage() {
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new([1; 32], 0),
+                Script::new(vec![0x51]),
+                0,
+            )],
+            0,
+        );
+        let mut preimage = tx.to_bytes();
+        preimage.extend(1u32.to_le_bytes());
+        assert_eq!(
+            BitcoinTransaction::from_sighash_preimage(&preimage),
+            Ok((tx.clone(), 1))
+        );
+
+        assert_eq!(
+            BitcoinTransaction::from_sighash_preimage(&tx.to_bytes()),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        preimage.push(0);
+        assert_eq!(
+            BitcoinTransaction::from_sighash_preimage(&preimage),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_marginal_cost_of_input() {
+        // P2PKH: 36 + 1 + 108 + 4 = 149 vbytes
+        assert_eq!(InputType::P2pkh.weight(), 596);
+        assert_eq!(marginal_cost_of_input(InputType::P2pkh, 10.0), 1490);
+        // P2WPKH: 41 * 4 + 109 = 273 WU rounds up to 69 vbytes
+        assert_eq!(marginal_cost_of_input(InputType::P2wpkh, 1.0), 69);
+        // P2TR keypath: 164 + 67 = 231 WU rounds up to 58 vbytes
+        assert_eq!(marginal_cost_of_input(InputType::P2trKeypath, 2.0), 116);
+        assert_eq!(marginal_cost_of_input(InputType::P2pkh, 0.0), 0);
+        // 149 vbytes at 1.0004 sat/vB is 149.06, never rounded down to 149
+        assert_eq!(marginal_cost_of_input(InputType::P2pkh, 1.0004), 150);
+        // 149 * 0.0001 = 0.0149 rounds up to one sat; 149 * 0.01 = 1.49 rounds up to two
+        assert_eq!(marginal_cost_of_input(InputType::P2pkh, 0.0001), 1);
+        assert_eq!(marginal_cost_of_input(InputType::P2pkh, 0.01), 2);
+        // 149 * 1.0067 = 149.9983; rounding the rate to 1007 sat/kvB first would give 151
+        assert_eq!(marginal_cost_of_input(InputType::P2pkh, 1.0067), 150);
+    }
+
+    #[test]
+    fn test_to_table() {
+        let inputs = (0..11)
+            .map(|i| TransactionInput::new(OutPoint::new([0; 32], i), Script::new(vec![0; 2]), 0))
+            .collect();
+        let table = BitcoinTransaction::new(1, inputs, 0).to_table();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 12);
+
+        let zeros = "0".repeat(64);
+        assert_eq!(
+            lines[0],
+            format!(" #  {:<67}  Sequence    ScriptSig (bytes)", "Outpoint")
+        );
+        assert_eq!(
+            lines[1],
+            format!(" 0  {zeros}:0   0x00000000                  2")
+        );
+        assert_eq!(
+            lines[11],
+            format!("10  {zeros}:10  0x00000000                  2")
+        );
+    }
+
+    #[test]
+    fn test_to_descriptor_fragment() {
+        let hash20 = "11".repeat(20);
+        let hash32 = "22".repeat(32);
+        let cases = [
+            (
+                vec![
+                    vec![OP_DUP, OP_HASH160, 0x14],
+                    vec![0x11; 20],
+                    vec![OP_EQUALVERIFY, OP_CHECKSIG],
+                ],
+                format!("pkh({hash20})"),
+            ),
+            (
+                vec![vec![OP_HASH160, 0x14], vec![0x11; 20], vec![OP_EQUAL]],
+                format!("sh({hash20})"),
+            ),
+            (
+                vec![vec![OP_0, 0x14], vec![0x11; 20]],
+                format!("wpkh({hash20})"),
+            ),
+            (
+                vec![vec![OP_0, 0x20], vec![0x22; 32]],
+                format!("wsh({hash32})"),
+            ),
+            (
+                vec![vec![OP_1, 0x20], vec![0x22; 32]],
+                format!("tr({hash32})"),
+            ),
+        ];
+        for (parts, expected) in cases {
+            let script = Script::new(parts.concat());
+            assert_eq!(script.to_descriptor_fragment(), Some(expected));
+        }
+
+        assert_eq!(
+            Script::new([vec![OP_1, 0x14], vec![0; 20]].concat()).to_descriptor_fragment(),
+            None
+        );
+        assert_eq!(
+            Script::new(vec![OP_CHECKSIG]).to_descriptor_fragment(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_weight_breakdown() {
+        let tx = BitcoinTransaction::new(
+            2,
+            vec![
+                TransactionInput::new(OutPoint::null(), Script::new(vec![0; 107]), 0),
+                TransactionInput::new(OutPoint::null(), Script::new(vec![0; 300]), 0),
+            ],
+            0,
+        );
+        let breakdown = tx.weight_breakdown();
+        assert_eq!(breakdown.overhead, 36);
+        assert_eq!(breakdown.inputs, vec![148 * 4, 343 * 4]);
+        assert_eq!(breakdown.total(), tx.weight());
+    }
+
+    #[test]
+    fn test_large_input_count_roundtrip() {
+        let inputs = (0..50_000)
+            .map(|i| TransactionInput::new(OutPoint::new([1; 32], i), Script::new(vec![]), i))
+            .collect();
+        let tx = BitcoinTransaction::new(2, inputs, 0);
+        let bytes = tx.to_bytes();
+        assert_eq!(&bytes[4..7], &[0xFD, 0x50, 0xC3]);
+        assert_eq!(bytes.len(), 4 + 3 + 50_000 * 41 + 4);
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&bytes),
+            Ok((tx, bytes.len()))
+        );
+
+        // A huge claimed count must fail on missing data rather than allocating
+        let mut hostile = vec![2, 0, 0, 0, 0xFF];
+        hostile.extend(u64::MAX.to_le_bytes());
+        assert_eq!(
+            BitcoinTransaction::from_bytes(&hostile),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_verify_merkle_proof() {
+        let leaves: Vec<[u8; 32]> = (1..=4u8).map(|i| sha256d(&[i])).collect();
+        let parent = |a: &[u8; 32], b: &[u8; 32]| sha256d(&[&a[..], &b[..]].concat());
+        let left = parent(&leaves[0], &leaves[1]);
+        let right = parent(&leaves[2], &leaves[3]);
+        let root = parent(&left, &right);
+
+        // Leaf 2 is a left child at level 0 and a right child at level 1
+        let txid = Txid(leaves[2]);
+        assert!(verify_merkle_proof(&txid, &[leaves[3], left], 2, &root));
+        assert!(!verify_merkle_proof(&txid, &[leaves[3], left], 3, &root));
+        assert!(!verify_merkle_proof(&txid, &[leaves[3], left], 6, &root));
+        assert!(!verify_merkle_proof(&txid, &[left, leaves[3]], 2, &root));
+        assert!(verify_merkle_proof(&txid, &[], 0, &leaves[2]));
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_merkle_proof_roundtrip() {
+        let txids: Vec<Txid> = (0..7u8).map(|i| Txid(sha256d(&[i]))).collect();
+        let root = merkle_root(&txids).unwrap();
+        for (index, txid) in txids.iter().enumerate() {
+            let branch = merkle_proof(&txids, index).unwrap();
+            assert_eq!(branch.len(), 3);
+            assert!(verify_merkle_proof(txid, &branch, index, &root));
+        }
+        assert_eq!(merkle_proof(&txids, 7), None);
+
+        // Odd count: the third leaf is hashed with itself
+        let parent = |a: &[u8; 32], b: &[u8; 32]| sha256d(&[&a[..], &b[..]].concat());
+        let three = &txids[..3];
+        let expected = parent(
+            &parent(&three[0].0, &three[1].0),
+            &parent(&three[2].0, &three[2].0),
+        );
+        assert_eq!(merkle_root(three), Some(expected));
+        assert_eq!(merkle_proof(three, 2).unwrap()[0], three[2].0);
+
+        assert_eq!(merkle_root(&txids[..1]), Some(txids[0].0));
+        assert_eq!(merkle_root(&[]), None);
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn test_merkle_root_block_100000() {
+        let from_display = |hex_str: &str| {
+            let mut bytes: [u8; 32] = hex::decode(hex_str).unwrap().try_into().unwrap();
+            bytes.reverse();
+            bytes
+        };
+        let txids: Vec<Txid> = [
+            "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+            "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+            "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+            "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d",
+        ]
+        .iter()
+        .map(|hex_str| Txid(from_display(hex_str)))
+        .collect();
+        let root = from_display("f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766");
+        assert_eq!(merkle_root(&txids), Some(root));
+    }
+
+    #[test]
+    fn test_depends_on() {
+        let input = |txid: [u8; 32], vout| {
+            TransactionInput::new(OutPoint::new(txid, vout), Script::new(vec![]), 0)
+        };
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![input([2; 32], 0), input([1; 32], 0), input([2; 32], 1)],
+            0,
+        );
+        assert_eq!(tx.depends_on(), vec![Txid([2; 32]), Txid([1; 32])]);
+
+        let coinbase = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::null(),
+                Script::new(vec![]),
+                0,
+            )],
+            0,
+        );
+        assert!(coinbase.depends_on().is_empty());
+    }
+
+    #[test]
+    fn test_parse_max_total_size() {
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(OutPoint::null(), Script::new(vec![0; 100]), 0); 3],
+            0,
+        );
+        let bytes = tx.to_bytes();
+        let options = ParseOptions {
+            max_total_size: 200,
+            ..ParseOptions::permissive()
+        };
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with(&bytes, &options),
+            Err(BitcoinError::SizeExceeded)
+        );
+
+        let exact = ParseOptions {
+            max_total_size: bytes.len(),
+            ..ParseOptions::strict()
+        };
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with(&bytes, &exact),
+            Ok((tx, bytes.len()))
+        );
+        assert_eq!(ParseOptions::default().max_total_size, MAX_BLOCK_WEIGHT);
+    }
+
+    #[test]
+    fn test_outpoint_ordering() {
+        let mut outpoints = vec![
+            OutPoint::new([2; 32], 0),
+            OutPoint::new([1; 32], 7),
+            OutPoint::new([1; 32], 256),
+            OutPoint::new([1; 32], 2),
+        ];
+        outpoints.sort();
+        assert_eq!(
+            outpoints,
+            vec![
+                OutPoint::new([1; 32], 2),
+                OutPoint::new([1; 32], 7),
+                OutPoint::new([1; 32], 256),
+                OutPoint::new([2; 32], 0),
+            ]
+        );
+
+        let utxos: std::collections::BTreeMap<OutPoint, u64> =
+            outpoints.into_iter().zip(0..).collect();
+        let same_tx: Vec<u32> = utxos
+            .range(OutPoint::new([1; 32], 0)..OutPoint::new([2; 32], 0))
+            .map(|(outpoint, _)| outpoint.vout)
+            .collect();
+        assert_eq!(same_tx, vec![2, 7, 256]);
+    }
+
+    #[test]
+    fn test_signing_overhead() {
+        let unsigned = |n| vec![TransactionInput::new(OutPoint::null(), Script::new(vec![]), 0); n];
+
+        // Legacy: only the scriptSig grows, from 1 prefix byte to 1 + 108
+        let legacy = BitcoinTransaction::new(2, unsigned(1), 0);
+        assert_eq!(legacy.signing_overhead(&[InputType::P2pkh]), 108);
+
+        // P2WPKH: scriptSig stays empty; marker/flag plus the 109-byte witness
+        let segwit = BitcoinTransaction::new(2, unsigned(2), 0);
+        assert_eq!(
+            segwit.signing_overhead(&[InputType::P2wpkh, InputType::P2pkh]),
+            2 + 109 + 108 + 1
+        );
+        // The untyped second input still needs its empty witness stack
+        assert_eq!(segwit.signing_overhead(&[InputType::P2wpkh]), 2 + 109 + 1);
+        // Types beyond the inputs are ignored
+        assert_eq!(
+            legacy.signing_overhead(&[InputType::P2pkh, InputType::P2wpkh]),
+            108
+        );
+
+        let p2wpkh_out = Script::new([vec![OP_0, 0x14], vec![0; 20]].concat());
+        let estimate = estimate_vsize(&[InputType::P2pkh], &[p2wpkh_out]);
+        // The estimate also counts an output count byte and a 31-byte output
+        assert_eq!(legacy.to_bytes().len() + 108 + 1 + 31, estimate);
+    }
+
+    #[test]
+    fn test_bigsize_bolt1_vectors() {
+        let valid = [
+            (0, "00"),
+            (252, "fc"),
+            (253, "fd00fd"),
+            (65535, "fdffff"),
+            (65536, "fe00010000"),
+            (4294967295, "feffffffff"),
+            (4294967296, "ff0000000100000000"),
+            (u64::MAX, "ffffffffffffffffff"),
+        ];
+        for (value, encoded) in valid {
+            let bytes = hex::decode(encoded).unwrap();
+            assert_eq!(BigSize::new(value).to_bytes(), bytes);
+            assert_eq!(
+                BigSize::from_bytes(&bytes),
+                Ok((BigSize::new(value), bytes.len()))
+            );
+        }
+
+        for encoded in ["fd00fc", "fe0000ffff", "ff00000000ffffffff"] {
+            let bytes = hex::decode(encoded).unwrap();
+            assert_eq!(
+                BigSize::from_bytes(&bytes),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+        for encoded in ["fd00", "feffff", "ffffffffff", "", "fd", "fe", "ff"] {
+            let bytes = hex::decode(encoded).unwrap();
+            assert_eq!(
+                BigSize::from_bytes(&bytes),
+                Err(BitcoinError::InsufficientBytes)
+            );
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+mod roundtrip_properties {
+    use proptest::prelude::*;
+    use rust_week_3_exercises::strategies::*;
+    use rust_week_3_exercises::*;
+
+    proptest! {
+        #[test]
+        fn compact_size_roundtrip(cs in compact_size()) {
+            let bytes = cs.to_bytes();
+            prop_assert_eq!(CompactSize::from_bytes(&bytes).unwrap(), (cs, bytes.len()));
+        }
+
+        #[test]
+        fn outpoint_roundtrip(outpoint in outpoint()) {
+            let bytes = outpoint.to_bytes();
+            prop_assert_eq!(OutPoint::from_bytes(&bytes).unwrap(), (outpoint, bytes.len()));
+        }
+
+        #[test]
+        fn script_roundtrip(script in script()) {
+            let bytes = script.to_bytes();
+            prop_assert_eq!(Script::from_bytes(&bytes).unwrap(), (script, bytes.len()));
+        }
+
+        #[test]
+        fn transaction_input_roundtrip(input in transaction_input()) {
+            let bytes = input.to_bytes();
+            prop_assert_eq!(TransactionInput::from_bytes(&bytes).unwrap(), (input, bytes.len()));
+        }
+
+        #[test]
+        fn bitcoin_transaction_roundtrip(tx in bitcoin_transaction()) {
+            let bytes = tx.to_bytes();
+            prop_assert_eq!(BitcoinTransaction::from_bytes(&bytes).unwrap(), (tx, bytes.len()));
+        }
+    }
 }